@@ -7,8 +7,8 @@ use bevy::{
     render::render_resource::{Extent3d, TextureDimension, TextureFormat},
 };
 use bevy_av1::{
-    AddVideoSource, Decodable, Decoder, PlaybackMode, VideoFrame, VideoPlayer, VideoPlugin,
-    VideoSink,
+    AddVideoSource, Decodable, DecodeSettings, Decoder, PlaybackMode, VideoFrame, VideoPlayer,
+    VideoPlugin, VideoSink,
 };
 use rand::{SeedableRng, rngs::SmallRng, seq::IndexedRandom};
 use std::time::Duration;
@@ -37,7 +37,7 @@ impl CustomVideoSource {
 impl Decodable for CustomVideoSource {
     type Decoder = CustomDecoder;
 
-    fn decoder(&self) -> Self::Decoder {
+    fn decoder(&self, _settings: &DecodeSettings) -> Self::Decoder {
         CustomDecoder {
             width: self.width,
             height: self.height,
@@ -100,6 +100,7 @@ impl Decoder for CustomDecoder {
             let frame = VideoFrame {
                 image,
                 timestamp: Duration::from_secs_f32((count as f32) * frame_duration),
+                ..default()
             };
             tx.send(frame).await?;
 
@@ -121,6 +122,7 @@ fn setup(mut commands: Commands, mut custom_sources: ResMut<Assets<CustomVideoSo
         .spawn(VideoPlayer {
             source: custom_sources.add(CustomVideoSource::new(640, 480)),
             mode: PlaybackMode::Loop,
+            ..default()
         })
         .observe(
             |trigger: Trigger<OnAdd, VideoSink>,
@@ -2,7 +2,8 @@
 
 use bevy::{prelude::*, window::WindowResolution};
 use bevy_av1::{
-    PlaybackMode, VideoPlayer, VideoPlugin, VideoSink, VideoTargetApp, VideoTargetAssets,
+    PlaybackMode, VideoPlayer, VideoPlugin, VideoResized, VideoSink, VideoTargetApp,
+    VideoTargetAssets,
 };
 
 fn main() {
@@ -56,17 +57,27 @@ fn setup(
                 {
                     video_targets.add_target(sink, &mesh_material.0);
                     material.base_color_texture = Some(sink.image().clone());
-                    let aspect = sink.width() as f32 / sink.height() as f32;
-                    if aspect > 1.0 {
-                        transform.scale = Vec3::new(aspect, 1.0, 1.0);
-                    } else {
-                        transform.scale = Vec3::new(1.0, aspect, 1.0);
-                    }
+                    scale_to_aspect(&mut transform, sink.display_aspect_ratio());
+                }
+            },
+        )
+        .observe(
+            |trigger: Trigger<VideoResized>, mut transforms: Query<&mut Transform>| {
+                if let Ok(mut transform) = transforms.get_mut(trigger.target()) {
+                    scale_to_aspect(&mut transform, trigger.display_aspect_ratio);
                 }
             },
         );
 }
 
+fn scale_to_aspect(transform: &mut Transform, aspect: f32) {
+    if aspect > 1.0 {
+        transform.scale = Vec3::new(aspect, 1.0, 1.0);
+    } else {
+        transform.scale = Vec3::new(1.0, aspect, 1.0);
+    }
+}
+
 fn update(mut videos: Query<&mut Transform, With<VideoPlayer>>, time: Res<Time>) {
     for mut transform in videos.iter_mut() {
         transform.rotate_x(time.delta_secs() * 0.8);
@@ -116,7 +116,7 @@ fn spawn_video_targets(
         ..default()
     });
     standard_material_video_targets.add_target(sink, &standard_material);
-    let aspect = sink.width() as f32 / sink.height() as f32;
+    let aspect = sink.display_aspect_ratio();
     commands.spawn((
         Mesh3d(meshes.add(Cuboid::new(aspect.max(1.0), aspect.min(1.0), 1.0))),
         MeshMaterial3d(standard_material),
@@ -130,7 +130,10 @@ fn spawn_video_targets(
     color_material_video_targets.add_target(sink, &color_material);
     commands.spawn((
         MeshMaterial2d(color_material),
-        Mesh2d(meshes.add(Rectangle::new(sink.width() as f32, sink.height() as f32))),
+        Mesh2d(meshes.add(Rectangle::new(
+            sink.height() as f32 * aspect,
+            sink.height() as f32,
+        ))),
         Transform::from_xyz(offset2d, offset2d / 2.0, 0.0).with_scale(Vec3::splat(0.3)),
     ));
 }
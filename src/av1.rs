@@ -1,15 +1,19 @@
 pub mod decoder;
 pub mod ivf;
+pub mod obu;
 use std::fmt::Display;
 
 pub use decoder::Decoder;
 
 #[derive(Debug)]
 pub enum Error {
+    Io(std::io::Error),
     Demuxer(std::io::Error),
     ChannelClosed,
     Decoder(dav1d::Error),
     Conversion(yuv::YuvError),
+    /// Only 8 bit video can be converted to an image, see [`DecodeSettings::yuv_planes_only`][crate::DecodeSettings::yuv_planes_only].
+    UnsupportedBitDepth(usize),
 }
 
 impl Display for Error {
@@ -1,6 +1,8 @@
 use std::{
-    io::{Read, Seek},
-    time::Duration,
+    collections::VecDeque,
+    io::{self, Read, Seek},
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
 };
 use yuv::{
     YuvGrayImage, YuvPlanarImage, YuvRange, YuvStandardMatrix, yuv400_to_bgra, yuv420_to_bgra,
@@ -14,142 +16,799 @@ use bevy::{
 };
 use std::result::Result;
 
-use crate::{av1, decodable::VideoFrame};
+use crate::{
+    av1,
+    chroma_key::ChromaKey,
+    decodable::{DecodeSettings, FilmGrain, OutputScale, VideoFrame},
+    frame_info::{ColorRange, FrameInfo, FrameType, PixelLayout, YuvPlanes},
+    throttle::{DecodeThrottle, OffscreenPolicy},
+    video::{VideoRange, VideoTime},
+    video_source::AlphaLayout,
+};
 
 // Based on https://github.com/rust-av/dav1d-rs/blob/master/tools/src/main.rs
 
 pub struct Decoder<R: Read + Seek + Send> {
     decoder: dav1d::Decoder,
     demuxer: av1::ivf::Demuxer<R>,
+    pending_data: bool,
+    parser: av1::obu::Parser,
+    /// Headers of shown frames sent to the decoder, with their pts.
+    frame_headers: VecDeque<(i64, av1::obu::FrameHeader)>,
+    /// Queue parsed headers in `frame_headers`. The alpha stream decoder only parses headers
+    /// to find key frames, its headers are never read.
+    queue_frame_headers: bool,
+    yuv_planes: bool,
+    /// Skip converting frames to BGRA.
+    yuv_planes_only: bool,
+    export_film_grain: bool,
+    output_scale: OutputScale,
+    chroma_key: Option<ChromaKey>,
+    alpha_layout: AlphaLayout,
+    /// Size of the first frame in the bitstream.
+    /// Used in place of the container header size, which may not match the bitstream.
+    size: Option<av1::obu::FrameSize>,
+    /// Decoder of a separate alpha stream.
+    alpha: Option<Box<Decoder<R>>>,
+    throttle: DecodeThrottle,
+    /// Packets have been skipped, decoding must restart at a key frame.
+    awaiting_keyframe: bool,
+    /// Frames decoded since the last frame output at a reduced rate.
+    skipped_frames: u32,
+    /// Time spent decoding pictures since the last frame output.
+    decode_time: Duration,
+    /// Continue from the loop start at the end of the range.
+    looping: bool,
+    timeline: Timeline,
+    /// Only output pictures from this timeline pts,
+    /// earlier pictures were decoded after a seek to be used as references.
+    next_pts: i64,
+    /// Seek to this stream pts before reading the next packet.
+    seek_to: Option<u64>,
+    /// End of the range to decode, as stream pts.
+    end_pts: Option<u64>,
+    /// Where looping restarts, as stream pts.
+    loop_start_pts: u64,
+    /// Key frames of the stream, indexed on the first seek.
+    index: Arc<StreamIndex>,
+    frame_count: Option<u64>,
+    duration: Option<Duration>,
+    /// The duration of a frame, as stream pts.
+    frame_pts: Option<u64>,
+}
+
+/// Information found by scanning a stream, shared by all the decoders of a
+/// [`VideoSource`][crate::VideoSource] so that the stream is only scanned once.
+#[derive(Debug, Default)]
+pub struct StreamIndex {
+    keyframes: OnceLock<Keyframes>,
+    length: OnceLock<Length>,
+}
+
+/// The key frames of a stream.
+#[derive(Debug)]
+struct Keyframes {
+    /// Container pts of the first packet.
+    first_pts: Option<u64>,
+    /// Byte offsets and container pts of key frames.
+    offsets: Vec<(u64, u64)>,
+}
+
+/// The number of frames and duration of a stream.
+#[derive(Debug, Clone, Copy)]
+struct Length {
+    frame_count: Option<u64>,
+    duration: Option<Duration>,
+    /// Smallest increase between consecutive pts, the duration of a frame.
+    frame_pts: Option<u64>,
+}
+
+impl StreamIndex {
+    /// The duration of the stream, if its length was measured.
+    pub fn duration(&self) -> Option<Duration> {
+        self.length.get().and_then(|length| length.duration)
+    }
+
+    /// Measure the length of the stream read from `reader`, if it was not measured yet,
+    /// so that starting a decoder does not scan the stream.
+    pub fn prime_length<R: Read + Seek + Send>(&self, reader: R) -> Result<(), av1::Error> {
+        let mut demuxer = av1::ivf::Demuxer::new(reader).map_err(av1::Error::Demuxer)?;
+        self.length(&mut demuxer)?;
+        Ok(())
+    }
+
+    /// The length of the stream, measured with `demuxer` the first time.
+    fn length<R: Read + Seek + Send>(
+        &self,
+        demuxer: &mut av1::ivf::Demuxer<R>,
+    ) -> Result<Length, av1::Error> {
+        if let Some(length) = self.length.get() {
+            return Ok(*length);
+        }
+        let length = Self::probe_length(demuxer)?;
+        Ok(*self.length.get_or_init(|| length))
+    }
+
+    /// Count the packets and measure the duration of the stream from the packet headers,
+    /// then rewind the demuxer. Falls back to the frame count of the IVF header.
+    fn probe_length<R: Read + Seek + Send>(
+        demuxer: &mut av1::ivf::Demuxer<R>,
+    ) -> Result<Length, av1::Error> {
+        let mut timeline = Timeline::default();
+        loop {
+            match demuxer.skip_packet() {
+                Ok(pts) => {
+                    let stream_pts = timeline.stream_pts(pts);
+                    timeline.pts(stream_pts);
+                }
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(av1::Error::Demuxer(e)),
+            }
+        }
+        demuxer.reset().map_err(av1::Error::Demuxer)?;
+        if timeline.packets == 0 {
+            let frame_count = demuxer.frame_count();
+            return Ok(Length {
+                frame_count: (frame_count > 0).then_some(frame_count as u64),
+                duration: None,
+                frame_pts: None,
+            });
+        }
+        let timebase = demuxer.timebase();
+        let duration = Duration::from_secs_f64(
+            timeline.end_pts() as f64 * (timebase.0 as f64 / timebase.1 as f64),
+        );
+        Ok(Length {
+            frame_count: Some(timeline.packets),
+            duration: Some(duration),
+            frame_pts: timeline.frame_pts,
+        })
+    }
+}
+
+/// Maps container pts to stream pts, relative to the first packet,
+/// and stream pts to a timeline that keeps increasing when looping.
+#[derive(Default)]
+struct Timeline {
+    /// pts of the first packet of the stream.
+    first_pts: Option<u64>,
+    /// Stream pts of the previous packet.
+    previous_pts: Option<u64>,
+    /// Largest stream pts of the current pass through the stream.
+    last_pts: u64,
+    /// Smallest increase between consecutive pts, the duration of a frame.
+    frame_pts: Option<u64>,
+    /// Added to stream pts, the total duration of the previous passes through the stream.
+    offset: i64,
+    /// Packets read in the current pass through the stream.
+    packets: u64,
+    /// The timeline pts where each pass after the first starts, with its offset.
+    passes: VecDeque<(i64, i64)>,
+}
+
+impl Timeline {
+    /// The stream pts of a packet with container `pts`.
+    fn stream_pts(&mut self, pts: u64) -> u64 {
+        pts.saturating_sub(*self.first_pts.get_or_insert(pts))
+    }
+
+    /// The timeline pts of the next packet with stream `pts`.
+    fn pts(&mut self, pts: u64) -> i64 {
+        if let Some(previous_pts) = self.previous_pts
+            && pts > previous_pts
+        {
+            let step = pts - previous_pts;
+            self.frame_pts = Some(self.frame_pts.map_or(step, |frame_pts| frame_pts.min(step)));
+        }
+        self.previous_pts = Some(pts);
+        self.last_pts = self.last_pts.max(pts);
+        self.packets += 1;
+        pts as i64 + self.offset
+    }
+
+    /// The stream pts following the last packet of the current pass.
+    fn end_pts(&self) -> u64 {
+        self.last_pts + self.frame_pts.unwrap_or(1)
+    }
+
+    /// Start the next pass through the stream at stream pts `start`,
+    /// after the current pass ended at `end`. Returns `false` if the pass was empty.
+    fn restart(&mut self, start: u64, end: u64) -> bool {
+        if self.packets == 0 {
+            return false;
+        }
+        self.offset += end as i64 - start as i64;
+        self.passes
+            .push_back((start as i64 + self.offset, self.offset));
+        self.previous_pts = None;
+        self.last_pts = 0;
+        self.packets = 0;
+        true
+    }
+
+    /// The stream pts of the output picture with timeline `pts`.
+    /// Pictures must be queried in output order.
+    fn output_stream_pts(&mut self, pts: i64) -> i64 {
+        // Earlier passes have been output
+        while self.passes.get(1).is_some_and(|&(start, _)| start <= pts) {
+            self.passes.pop_front();
+        }
+        let offset = self
+            .passes
+            .front()
+            .filter(|&&(start, _)| start <= pts)
+            .map_or(0, |&(_, offset)| offset);
+        pts - offset
+    }
+}
+
+/// A decoded picture, and the corresponding picture from the alpha stream if any.
+struct Picture {
+    color: dav1d::Picture,
+    alpha: Option<dav1d::Picture>,
 }
 
 impl<R: Read + Seek + Send> Decoder<R> {
-    pub fn new(reader: R) -> Result<Self, av1::Error> {
+    pub fn new(reader: R, decode_settings: &DecodeSettings) -> Result<Self, av1::Error> {
         let mut settings = dav1d::Settings::new();
         settings.set_n_threads(1);
+        settings.set_apply_grain(decode_settings.film_grain == FilmGrain::Apply);
+        let mut demuxer = av1::ivf::Demuxer::new(reader).map_err(av1::Error::Demuxer)?;
+        let size = Self::probe_size(&mut demuxer)?;
+        if let Some(size) = size
+            && (size.upscaled_width != demuxer.width() as u32
+                || size.frame_height != demuxer.height() as u32)
+        {
+            debug!(
+                "IVF header size {}x{} does not match AV1 frame size {}x{}",
+                demuxer.width(),
+                demuxer.height(),
+                size.upscaled_width,
+                size.frame_height
+            );
+        }
         Ok(Self {
             decoder: dav1d::Decoder::with_settings(&settings).map_err(av1::Error::Decoder)?,
-            demuxer: av1::ivf::Demuxer::new(reader).map_err(av1::Error::Demuxer)?,
+            demuxer,
+            pending_data: false,
+            parser: av1::obu::Parser::default(),
+            frame_headers: VecDeque::new(),
+            queue_frame_headers: true,
+            yuv_planes: decode_settings.yuv_planes || decode_settings.yuv_planes_only,
+            yuv_planes_only: decode_settings.yuv_planes_only,
+            export_film_grain: decode_settings.film_grain == FilmGrain::Export,
+            output_scale: decode_settings.output_scale,
+            chroma_key: decode_settings.chroma_key,
+            alpha_layout: AlphaLayout::Opaque,
+            size,
+            alpha: None,
+            throttle: DecodeThrottle::default(),
+            awaiting_keyframe: false,
+            skipped_frames: 0,
+            decode_time: Duration::ZERO,
+            looping: false,
+            timeline: Timeline::default(),
+            next_pts: 0,
+            seek_to: None,
+            end_pts: None,
+            loop_start_pts: 0,
+            index: Arc::default(),
+            frame_count: None,
+            duration: None,
+            frame_pts: None,
         })
     }
 
+    /// Parse the size of the first frame from the bitstream, then rewind the demuxer.
+    fn probe_size(
+        demuxer: &mut av1::ivf::Demuxer<R>,
+    ) -> Result<Option<av1::obu::FrameSize>, av1::Error> {
+        let size = match demuxer.read_packet() {
+            Ok(packet) => av1::obu::Parser::default()
+                .parse_temporal_unit(&packet.data)
+                .unwrap_or_else(|e| {
+                    debug!("Failed to parse AV1 frame header: {e}");
+                    None
+                })
+                .map(|header| header.size),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => None,
+            Err(e) => return Err(av1::Error::Demuxer(e)),
+        };
+        demuxer.reset().map_err(av1::Error::Demuxer)?;
+        Ok(size)
+    }
+
+    /// Decode alpha packed into each frame using `alpha_layout`.
+    pub fn with_alpha_layout(mut self, alpha_layout: AlphaLayout) -> Self {
+        self.alpha_layout = alpha_layout;
+        self
+    }
+
+    /// Share the `index` of the stream with other decoders of the same stream,
+    /// measuring the length of the stream unless the index already has it.
+    pub fn with_index(mut self, index: Arc<StreamIndex>) -> Result<Self, av1::Error> {
+        let length = index.length(&mut self.demuxer)?;
+        self.frame_count = length.frame_count;
+        self.duration = length.duration;
+        self.frame_pts = length.frame_pts;
+        self.index = index;
+        Ok(self)
+    }
+
+    /// Decode alpha from the luma of a separate video stream read from `reader`,
+    /// sharing the `index` of the alpha stream.
+    pub fn with_alpha_stream(
+        mut self,
+        reader: R,
+        index: Arc<StreamIndex>,
+    ) -> Result<Self, av1::Error> {
+        // Grain would add noise to the alpha channel
+        let settings = DecodeSettings {
+            film_grain: FilmGrain::Skip,
+            ..default()
+        };
+        let mut alpha = Decoder::new(reader, &settings)?;
+        // The length of the alpha stream is that of the color stream, so is not measured
+        alpha.index = index;
+        alpha.queue_frame_headers = false;
+        // Not throttled, its key frames may not match those of the color stream,
+        // so it decodes every packet to catch up with the color pictures
+        self.alpha = Some(Box::new(alpha));
+        Ok(self)
+    }
+
+    /// Continue from the loop start at the end of the range, instead of ending.
+    fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+        if let Some(alpha) = &mut self.alpha {
+            alpha.set_looping(looping);
+        }
+    }
+
+    /// Only decode `range` of the video.
+    pub fn set_range(&mut self, range: VideoRange) {
+        let start = range.start.map_or(0, |start| self.time_to_pts(start));
+        self.end_pts = range
+            .end
+            .map(|end| self.time_to_pts(end))
+            .filter(|&end| end > start);
+        self.loop_start_pts = range
+            .loop_start
+            .map(|loop_start| self.time_to_pts(loop_start))
+            .filter(|&loop_start| self.end_pts.is_none_or(|end| loop_start < end))
+            .unwrap_or(start);
+        self.next_pts = start as i64;
+        self.seek_to = (start > 0).then_some(start);
+        if let Some(alpha) = &mut self.alpha {
+            // Frames of the alpha stream last as long, but its length is not measured
+            alpha.frame_pts = self.frame_pts;
+            alpha.set_range(range);
+        }
+    }
+
+    /// The stream pts of `time`.
+    fn time_to_pts(&self, time: VideoTime) -> u64 {
+        let (num, den) = self.demuxer.timebase();
+        let time = time.to_duration(self.frame_duration());
+        (time.as_secs_f64() * den as f64 / num as f64).round() as u64
+    }
+
+    /// Continue reading packets at the last key frame at or before stream pts `target`.
+    fn seek(&mut self, target: u64) -> Result<(), av1::Error> {
+        let index = self.index.clone();
+        let keyframes = match index.keyframes.get() {
+            Some(keyframes) => keyframes,
+            None => {
+                let keyframes = self.index_keyframes()?;
+                // Another decoder of the stream may have indexed it meanwhile
+                index.keyframes.get_or_init(|| keyframes)
+            }
+        };
+        if let Some(first_pts) = keyframes.first_pts {
+            self.timeline.first_pts.get_or_insert(first_pts);
+        }
+        let first_pts = self.timeline.first_pts.unwrap_or_default();
+        let offset = keyframes
+            .offsets
+            .iter()
+            .rev()
+            .find(|&&(_, pts)| pts.saturating_sub(first_pts) <= target)
+            .map_or(av1::ivf::HEADER_SIZE, |&(offset, _)| offset);
+        self.demuxer.seek(offset).map_err(av1::Error::Demuxer)
+    }
+
+    /// Find the key frames of the stream, reading it from the start.
+    fn index_keyframes(&mut self) -> Result<Keyframes, av1::Error> {
+        let mut parser = av1::obu::Parser::default();
+        let mut keyframes = Keyframes {
+            first_pts: None,
+            offsets: Vec::new(),
+        };
+        self.demuxer.reset().map_err(av1::Error::Demuxer)?;
+        loop {
+            let offset = self.demuxer.position().map_err(av1::Error::Demuxer)?;
+            let packet = match self.demuxer.read_packet() {
+                Ok(packet) => packet,
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(av1::Error::Demuxer(e)),
+            };
+            keyframes.first_pts.get_or_insert(packet.pts);
+            if let Ok(Some(header)) = parser.parse_temporal_unit(&packet.data)
+                && header.frame_type == FrameType::Key
+            {
+                keyframes.offsets.push((offset, packet.pts));
+            }
+        }
+        Ok(keyframes)
+    }
+
+    /// Loop back to the loop start, after the current pass ended at stream pts `end`.
+    /// Returns `false` if the pass was empty.
+    fn restart(&mut self, end: u64) -> Result<bool, av1::Error> {
+        if !self.timeline.restart(self.loop_start_pts, end) {
+            return Ok(false);
+        }
+        if self.loop_start_pts > 0 {
+            self.seek(self.loop_start_pts)?;
+        } else {
+            self.demuxer.reset().map_err(av1::Error::Demuxer)?;
+        }
+        Ok(true)
+    }
+
     pub async fn decode(
         &mut self,
         tx: async_channel::Sender<VideoFrame>,
         loop_: bool,
     ) -> Result<(), av1::Error> {
-        loop {
-            while let Ok(packet) = self.demuxer.read_packet() {
-                // Send packet to the decoder
-                match self
-                    .decoder
-                    .send_data(packet.data, None, Some(packet.pts as i64), None)
-                {
-                    Err(e) if e.is_again() => {
-                        // If the decoder did not consume all data, output all
-                        // pending pictures and send pending data to the decoder
-                        // until it is all used up.
-                        loop {
-                            self.handle_pending_pictures(&tx, false).await?;
-
-                            match self.decoder.send_pending_data() {
-                                Err(e) if e.is_again() => continue,
-                                Err(e) => return Err(av1::Error::Decoder(e)),
-                                _ => break,
-                            }
-                        }
-                    }
-                    Err(e) => return Err(av1::Error::Decoder(e)),
-                    _ => (),
+        self.set_looping(loop_);
+        while let Some(p) = self.next_picture()? {
+            if let OffscreenPolicy::ReducedRate(n) = self.throttle.get() {
+                self.skipped_frames += 1;
+                if self.skipped_frames < n {
+                    continue;
                 }
-
-                // Handle all pending pictures before sending the next data.
-                self.handle_pending_pictures(&tx, false).await?;
             }
+            self.skipped_frames = 0;
+            tx.send(self.frame(&p)?)
+                .await
+                .map_err(|_| av1::Error::ChannelClosed)?;
+        }
+        Ok(())
+    }
 
-            // Handle all pending pictures that were not output yet.
-            self.handle_pending_pictures(&tx, true).await?;
+    /// Decode the next frame of video, returns `None` at the end of the stream.
+    pub fn next_frame(&mut self) -> Result<Option<VideoFrame>, av1::Error> {
+        match self.next_picture()? {
+            Some(p) => Ok(Some(self.frame(&p)?)),
+            None => Ok(None),
+        }
+    }
 
-            if loop_ {
-                self.demuxer.reset().map_err(av1::Error::Demuxer)?;
-            } else {
-                break;
+    /// Decode forward to the frames displayed at each of the ascending `timestamps`.
+    /// Timestamps past the end of the video return the last frame.
+    pub fn frames_at(&mut self, timestamps: &[Duration]) -> Result<Vec<VideoFrame>, av1::Error> {
+        let mut frames = Vec::with_capacity(timestamps.len());
+        let mut current = None;
+        let mut next = self.next_picture()?;
+        for &timestamp in timestamps {
+            while let Some(p) = next.take_if(|p| self.timestamp(&p.color) <= timestamp) {
+                current = Some(p);
+                next = self.next_picture()?;
             }
+            // Timestamps before the first frame use the first frame
+            let Some(p) = current.as_ref().or(next.as_ref()) else {
+                break;
+            };
+            frames.push(self.frame(p)?);
         }
-        Ok(())
+        Ok(frames)
     }
 
-    async fn handle_pending_pictures(
-        &mut self,
-        tx: &async_channel::Sender<VideoFrame>,
-        drain: bool,
-    ) -> Result<(), av1::Error> {
+    fn next_picture(&mut self) -> Result<Option<Picture>, av1::Error> {
+        let start = Instant::now();
+        let color = loop {
+            let Some(color) = self.decode_picture()? else {
+                return Ok(None);
+            };
+            // Skip pictures only decoded as references after a seek
+            let pts = color.timestamp().unwrap_or_default();
+            if pts >= self.next_pts {
+                self.next_pts = pts + 1;
+                break color;
+            }
+        };
+        let alpha = match &mut self.alpha {
+            Some(alpha) => {
+                // Color packets may have been skipped, find the matching alpha picture
+                let pts = color.timestamp();
+                let mut alpha_picture = alpha.decode_picture()?;
+                while alpha_picture.as_ref().is_some_and(|p| p.timestamp() < pts) {
+                    alpha_picture = alpha.decode_picture()?;
+                }
+                alpha_picture
+            }
+            None => None,
+        };
+        self.decode_time += start.elapsed();
+        Ok(Some(Picture { color, alpha }))
+    }
+
+    fn decode_picture(&mut self) -> Result<Option<dav1d::Picture>, av1::Error> {
         loop {
             match self.decoder.get_picture() {
-                Ok(p) => {
-                    let pts = p.timestamp().unwrap();
-                    let timebase = self.demuxer.timebase();
-                    let timebase = timebase.0 as f64 / timebase.1 as f64;
-                    let pts = Duration::from_secs_f64(pts as f64 * timebase);
-                    let frame = VideoFrame {
-                        image: Image::new(
-                            Extent3d {
-                                width: p.width(),
-                                height: p.height(),
-                                ..default()
-                            },
-                            TextureDimension::D2,
-                            self.yuv_to_bgr(&p)?,
-                            TextureFormat::Bgra8UnormSrgb, //XXX Bgra8Unorm or Bgra8UnormSrgb
-                            RenderAssetUsages::default(),
-                        ),
-                        timestamp: pts,
-                    };
-                    tx.send(frame)
-                        .await
-                        .map_err(|_| av1::Error::ChannelClosed)?;
-                }
+                Ok(p) => return Ok(Some(p)),
                 // Need to send more data to the decoder before it can decode new pictures
-                Err(e) if e.is_again() => return Ok(()),
-                Err(e) => {
-                    return Err(av1::Error::Decoder(e));
+                Err(e) if e.is_again() => (),
+                Err(e) => return Err(av1::Error::Decoder(e)),
+            }
+
+            // If the decoder did not consume all data, send pending data
+            // now that all pending pictures have been output.
+            if self.pending_data {
+                match self.decoder.send_pending_data() {
+                    Err(e) if e.is_again() => (),
+                    Err(e) => return Err(av1::Error::Decoder(e)),
+                    Ok(_) => self.pending_data = false,
                 }
+                continue;
             }
 
-            if !drain {
-                break;
+            if let Some(target) = self.seek_to.take() {
+                self.seek(target)?;
+            }
+            let packet = match self.demuxer.read_packet() {
+                Ok(packet) => packet,
+                // Continue with the first packets without draining the decoder,
+                // so the first key frame decodes while the last frames are output
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof && self.looping => {
+                    if !self.restart(self.timeline.end_pts())? {
+                        return Ok(None);
+                    }
+                    continue;
+                }
+                // End of stream, all pending pictures have been output
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(av1::Error::Demuxer(e)),
+            };
+            let stream_pts = self.timeline.stream_pts(packet.pts);
+            if let Some(end_pts) = self.end_pts
+                && stream_pts >= end_pts
+            {
+                if self.looping && self.restart(stream_pts)? {
+                    continue;
+                }
+                // End of the range, as if the end of the stream
+                return Ok(None);
+            }
+            let pts = self.timeline.pts(stream_pts);
+            let frame_type = match self.parser.parse_temporal_unit(&packet.data) {
+                Ok(Some(frame_header)) => {
+                    let frame_type = frame_header.frame_type;
+                    if self.queue_frame_headers {
+                        self.frame_headers.push_back((pts, frame_header));
+                    }
+                    Some(frame_type)
+                }
+                Ok(None) => None,
+                Err(e) => {
+                    debug!("Failed to parse AV1 frame header: {e}");
+                    None
+                }
+            };
+            // Skip packets until the next key frame
+            if self.throttle.get() == OffscreenPolicy::KeyframesOnly || self.awaiting_keyframe {
+                if frame_type != Some(FrameType::Key) {
+                    self.awaiting_keyframe = true;
+                    continue;
+                }
+                self.awaiting_keyframe = false;
+            }
+            // Send packet to the decoder
+            match self.decoder.send_data(packet.data, None, Some(pts), None) {
+                Err(e) if e.is_again() => self.pending_data = true,
+                Err(e) => return Err(av1::Error::Decoder(e)),
+                Ok(_) => (),
             }
         }
-        Ok(())
     }
 
-    fn yuv_to_bgr(&self, p: &dav1d::Picture) -> Result<Vec<u8>, av1::Error> {
-        assert!(p.bit_depth() == 8, "AV1 bit depth must be 8");
-        let range = match p.color_range() {
-            dav1d::pixel::YUVRange::Limited => YuvRange::Limited,
-            dav1d::pixel::YUVRange::Full => YuvRange::Full,
+    fn timestamp(&self, p: &dav1d::Picture) -> Duration {
+        self.pts_duration(p.timestamp().unwrap())
+    }
+
+    /// The duration of a frame, measured when the length of the stream is known,
+    /// otherwise one tick of the timebase.
+    fn frame_duration(&self) -> Duration {
+        self.pts_duration(self.frame_pts.unwrap_or(1) as i64)
+    }
+
+    fn pts_duration(&self, pts: i64) -> Duration {
+        let timebase = self.demuxer.timebase();
+        let timebase = timebase.0 as f64 / timebase.1 as f64;
+        Duration::from_secs_f64(pts as f64 * timebase)
+    }
+
+    /// The size of decoded frames, from the bitstream if available, otherwise the container.
+    fn frame_size(&self) -> (u32, u32) {
+        let (width, height) = match self.size {
+            Some(size) => visible_size(size.upscaled_width, size.frame_height, Some(size)),
+            None => (self.demuxer.width() as u32, self.demuxer.height() as u32),
+        };
+        let (width, height) = color_size(self.alpha_layout, width, height);
+        self.output_scale.size(width, height)
+    }
+
+    fn frame(&mut self, p: &Picture) -> Result<VideoFrame, av1::Error> {
+        let start = Instant::now();
+        let stream_pts = self
+            .timeline
+            .output_stream_pts(p.color.timestamp().unwrap_or_default());
+        let position = self.pts_duration(stream_pts);
+        let header = self.frame_header(&p.color);
+        let (width, height) = visible_size(
+            p.color.width(),
+            p.color.height(),
+            header.as_ref().map(|header| header.size),
+        );
+        let (width, height) = color_size(self.alpha_layout, width, height);
+        let size = UVec2::from(self.output_scale.size(width, height));
+        let extent = Extent3d {
+            width: size.x,
+            height: size.y,
+            ..default()
         };
-        let matrix = match p.matrix_coefficients() {
-            dav1d::pixel::MatrixCoefficients::BT709 => YuvStandardMatrix::Bt709,
-            dav1d::pixel::MatrixCoefficients::BT470BG
-            | dav1d::pixel::MatrixCoefficients::ST170M => YuvStandardMatrix::Bt601,
-            dav1d::pixel::MatrixCoefficients::ST240M => YuvStandardMatrix::Smpte240,
-            dav1d::pixel::MatrixCoefficients::BT2020NonConstantLuminance
-            | dav1d::pixel::MatrixCoefficients::BT2020ConstantLuminance => {
-                YuvStandardMatrix::Bt2020
-            }
-            _ => YuvStandardMatrix::Bt601,
+        let image = if self.yuv_planes_only {
+            Image::new_uninit(
+                extent,
+                TextureDimension::D2,
+                TextureFormat::Bgra8UnormSrgb,
+                RenderAssetUsages::default(),
+            )
+        } else {
+            Image::new(
+                extent,
+                TextureDimension::D2,
+                self.bgra(p, width, height, size)?,
+                TextureFormat::Bgra8UnormSrgb, //XXX Bgra8Unorm or Bgra8UnormSrgb
+                RenderAssetUsages::default(),
+            )
         };
-        let mut bgra_data = vec![0; (p.width() * p.height() * 4) as usize];
+        Ok(VideoFrame {
+            image,
+            timestamp: self.timestamp(&p.color),
+            position: Some(position),
+            info: header.map(|header| self.frame_info(&p.color, header, stream_pts)),
+            planes: self.yuv_planes.then(|| Self::planes(&p.color)),
+            // Includes the time to decode skipped pictures
+            decode_time: std::mem::take(&mut self.decode_time),
+            conversion_time: start.elapsed(),
+        })
+    }
+
+    /// Convert the top left `width` x `height` region of `p` to BGRA at `size`,
+    /// applying alpha and chroma keying.
+    fn bgra(
+        &self,
+        p: &Picture,
+        width: u32,
+        height: u32,
+        size: UVec2,
+    ) -> Result<Vec<u8>, av1::Error> {
+        if let Some(bit_depth) = std::iter::once(&p.color)
+            .chain(&p.alpha)
+            .map(|p| p.bit_depth())
+            .find(|&bit_depth| bit_depth != 8)
+        {
+            return Err(av1::Error::UnsupportedBitDepth(bit_depth));
+        }
+        let mut data = if size == UVec2::new(width, height) {
+            self.yuv_to_bgr(&p.color, width, height)?
+        } else {
+            yuv_to_bgr_scaled(&p.color, URect::new(0, 0, width, height), size)?
+        };
+        match (&p.alpha, self.alpha_layout) {
+            (Some(alpha), _) => {
+                apply_alpha(&mut data, alpha, URect::new(0, 0, width, height), size);
+            }
+            (None, AlphaLayout::SideBySide) => {
+                let region = URect::new(width, 0, width * 2, height);
+                apply_alpha(&mut data, &p.color, region, size);
+            }
+            (None, AlphaLayout::Stacked) => {
+                let region = URect::new(0, height, width, height * 2);
+                apply_alpha(&mut data, &p.color, region, size);
+            }
+            (None, AlphaLayout::Opaque) => (),
+        }
+        if let Some(chroma_key) = &self.chroma_key {
+            chroma_key.apply(&mut data);
+        }
+        Ok(data)
+    }
+
+    /// The parsed header of the picture `p`.
+    fn frame_header(&mut self, p: &dav1d::Picture) -> Option<av1::obu::FrameHeader> {
+        let pts = p.timestamp()?;
+        // Discard headers of frames that were never output
+        let index = self
+            .frame_headers
+            .iter()
+            .position(|(header_pts, _)| *header_pts == pts)?;
+        self.frame_headers.drain(..index);
+        self.frame_headers.front().map(|(_, header)| header.clone())
+    }
+
+    /// The metadata of the picture `p`, at `stream_pts` in the stream.
+    fn frame_info(
+        &self,
+        p: &dav1d::Picture,
+        header: av1::obu::FrameHeader,
+        stream_pts: i64,
+    ) -> FrameInfo {
+        let (render_width, render_height) = color_size(
+            self.alpha_layout,
+            header.size.render_width,
+            header.size.render_height,
+        );
+        FrameInfo {
+            pts: stream_pts + self.timeline.first_pts.unwrap_or_default() as i64,
+            timeline_pts: p.timestamp().unwrap_or_default(),
+            frame_type: header.frame_type,
+            pixel_layout: pixel_layout(p),
+            bit_depth: p.bit_depth() as u8,
+            color_range: match p.color_range() {
+                dav1d::pixel::YUVRange::Limited => ColorRange::Limited,
+                dav1d::pixel::YUVRange::Full => ColorRange::Full,
+            },
+            matrix_coefficients: header.color_config.matrix_coefficients,
+            color_primaries: header.color_config.color_primaries,
+            transfer_characteristics: header.color_config.transfer_characteristics,
+            display_aspect_ratio: render_width as f32 / render_height as f32,
+            film_grain: header.film_grain.filter(|_| self.export_film_grain),
+        }
+    }
+
+    fn planes(p: &dav1d::Picture) -> YuvPlanes {
+        let planes = YuvPlanes {
+            width: p.width(),
+            height: p.height(),
+            layout: pixel_layout(p),
+            bit_depth: p.bit_depth() as u8,
+            y: p.plane(dav1d::PlanarImageComponent::Y).to_vec(),
+            y_stride: p.stride(dav1d::PlanarImageComponent::Y),
+            ..default()
+        };
+        if matches!(p.pixel_layout(), dav1d::PixelLayout::I400) {
+            planes
+        } else {
+            YuvPlanes {
+                u: p.plane(dav1d::PlanarImageComponent::U).to_vec(),
+                v: p.plane(dav1d::PlanarImageComponent::V).to_vec(),
+                uv_stride: p.stride(dav1d::PlanarImageComponent::U),
+                ..planes
+            }
+        }
+    }
+
+    /// Convert the top left `width` x `height` region of `p` to BGRA.
+    fn yuv_to_bgr(
+        &self,
+        p: &dav1d::Picture,
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<u8>, av1::Error> {
+        let (range, matrix) = yuv_conversion(p);
+        let mut bgra_data = vec![0; (width * height * 4) as usize];
         match p.pixel_layout() {
             dav1d::PixelLayout::I400 => {
                 let yuv_data = YuvGrayImage {
                     y_plane: &p.plane(dav1d::PlanarImageComponent::Y),
                     y_stride: p.stride(dav1d::PlanarImageComponent::Y),
-                    width: p.width(),
-                    height: p.height(),
+                    width,
+                    height,
                 };
-                yuv400_to_bgra(&yuv_data, &mut bgra_data, p.width() * 4, range, matrix)
+                yuv400_to_bgra(&yuv_data, &mut bgra_data, width * 4, range, matrix)
                     .map_err(av1::Error::Conversion)?
             }
             layout => {
@@ -160,20 +819,20 @@ impl<R: Read + Seek + Send> Decoder<R> {
                     u_stride: p.stride(dav1d::PlanarImageComponent::U),
                     v_plane: &p.plane(dav1d::PlanarImageComponent::V),
                     v_stride: p.stride(dav1d::PlanarImageComponent::V),
-                    width: p.width(),
-                    height: p.height(),
+                    width,
+                    height,
                 };
                 match layout {
                     dav1d::PixelLayout::I420 => {
-                        yuv420_to_bgra(&yuv_data, &mut bgra_data, p.width() * 4, range, matrix)
+                        yuv420_to_bgra(&yuv_data, &mut bgra_data, width * 4, range, matrix)
                             .map_err(av1::Error::Conversion)?
                     }
                     dav1d::PixelLayout::I422 => {
-                        yuv422_to_bgra(&yuv_data, &mut bgra_data, p.width() * 4, range, matrix)
+                        yuv422_to_bgra(&yuv_data, &mut bgra_data, width * 4, range, matrix)
                             .map_err(av1::Error::Conversion)?
                     }
                     dav1d::PixelLayout::I444 => {
-                        yuv444_to_bgra(&yuv_data, &mut bgra_data, p.width() * 4, range, matrix)
+                        yuv444_to_bgra(&yuv_data, &mut bgra_data, width * 4, range, matrix)
                             .map_err(av1::Error::Conversion)?
                     }
                     dav1d::PixelLayout::I400 => {}
@@ -184,19 +843,232 @@ impl<R: Read + Seek + Send> Decoder<R> {
     }
 }
 
+/// The size of the color region of a frame packed using `alpha_layout`.
+fn color_size(alpha_layout: AlphaLayout, width: u32, height: u32) -> (u32, u32) {
+    match alpha_layout {
+        AlphaLayout::Opaque => (width, height),
+        AlphaLayout::SideBySide => (width / 2, height),
+        AlphaLayout::Stacked => (width, height / 2),
+    }
+}
+
+/// The size of the visible region of a `width` x `height` picture.
+///
+/// The picture is cropped to the render size of the frame when it is smaller,
+/// removing any padding of the coded frame.
+/// A larger render size is an anamorphic frame that is intended to be stretched when displayed,
+/// see [`FrameInfo::display_aspect_ratio`].
+fn visible_size(width: u32, height: u32, size: Option<av1::obu::FrameSize>) -> (u32, u32) {
+    match size {
+        Some(size) if size.render_width <= width && size.render_height <= height => {
+            (size.render_width, size.render_height)
+        }
+        _ => (width, height),
+    }
+}
+
+/// The chroma subsampling of `p`.
+fn pixel_layout(p: &dav1d::Picture) -> PixelLayout {
+    match p.pixel_layout() {
+        dav1d::PixelLayout::I400 => PixelLayout::I400,
+        dav1d::PixelLayout::I420 => PixelLayout::I420,
+        dav1d::PixelLayout::I422 => PixelLayout::I422,
+        dav1d::PixelLayout::I444 => PixelLayout::I444,
+    }
+}
+
+/// The range and matrix used to convert `p` to RGB.
+fn yuv_conversion(p: &dav1d::Picture) -> (YuvRange, YuvStandardMatrix) {
+    let range = match p.color_range() {
+        dav1d::pixel::YUVRange::Limited => YuvRange::Limited,
+        dav1d::pixel::YUVRange::Full => YuvRange::Full,
+    };
+    let matrix = match p.matrix_coefficients() {
+        dav1d::pixel::MatrixCoefficients::BT709 => YuvStandardMatrix::Bt709,
+        dav1d::pixel::MatrixCoefficients::BT470BG | dav1d::pixel::MatrixCoefficients::ST170M => {
+            YuvStandardMatrix::Bt601
+        }
+        dav1d::pixel::MatrixCoefficients::ST240M => YuvStandardMatrix::Smpte240,
+        dav1d::pixel::MatrixCoefficients::BT2020NonConstantLuminance
+        | dav1d::pixel::MatrixCoefficients::BT2020ConstantLuminance => YuvStandardMatrix::Bt2020,
+        _ => YuvStandardMatrix::Bt601,
+    };
+    (range, matrix)
+}
+
+/// Convert the `region` of `p` to BGRA, downscaled to `size`.
+///
+/// Each plane is resampled directly to the output size before conversion,
+/// so subsampled chroma is not upsampled to the full source resolution.
+fn yuv_to_bgr_scaled(
+    p: &dav1d::Picture,
+    region: URect,
+    size: UVec2,
+) -> Result<Vec<u8>, av1::Error> {
+    let (range, matrix) = yuv_conversion(p);
+    let y_plane = resample(
+        &p.plane(dav1d::PlanarImageComponent::Y),
+        p.stride(dav1d::PlanarImageComponent::Y),
+        region,
+        size,
+    );
+    let mut bgra_data = vec![0; (size.x * size.y * 4) as usize];
+    let (subsampling_x, subsampling_y) = match p.pixel_layout() {
+        dav1d::PixelLayout::I400 => {
+            let yuv_data = YuvGrayImage {
+                y_plane: &y_plane,
+                y_stride: size.x,
+                width: size.x,
+                height: size.y,
+            };
+            yuv400_to_bgra(&yuv_data, &mut bgra_data, size.x * 4, range, matrix)
+                .map_err(av1::Error::Conversion)?;
+            return Ok(bgra_data);
+        }
+        dav1d::PixelLayout::I420 => (1, 1),
+        dav1d::PixelLayout::I422 => (1, 0),
+        dav1d::PixelLayout::I444 => (0, 0),
+    };
+    let chroma_region = URect::new(
+        region.min.x >> subsampling_x,
+        region.min.y >> subsampling_y,
+        region.max.x.div_ceil(1 << subsampling_x),
+        region.max.y.div_ceil(1 << subsampling_y),
+    );
+    let u_plane = resample(
+        &p.plane(dav1d::PlanarImageComponent::U),
+        p.stride(dav1d::PlanarImageComponent::U),
+        chroma_region,
+        size,
+    );
+    let v_plane = resample(
+        &p.plane(dav1d::PlanarImageComponent::V),
+        p.stride(dav1d::PlanarImageComponent::V),
+        chroma_region,
+        size,
+    );
+    let yuv_data = YuvPlanarImage {
+        y_plane: &y_plane,
+        y_stride: size.x,
+        u_plane: &u_plane,
+        u_stride: size.x,
+        v_plane: &v_plane,
+        v_stride: size.x,
+        width: size.x,
+        height: size.y,
+    };
+    yuv444_to_bgra(&yuv_data, &mut bgra_data, size.x * 4, range, matrix)
+        .map_err(av1::Error::Conversion)?;
+    Ok(bgra_data)
+}
+
+/// Resample the `region` of an 8 bit `plane` to `size`, averaging the samples covered by each output sample.
+fn resample(plane: &[u8], stride: u32, region: URect, size: UVec2) -> Vec<u8> {
+    let source_size = region.size();
+    if source_size == size {
+        return (region.min.y..region.max.y)
+            .flat_map(|row| {
+                let start = (row * stride + region.min.x) as usize;
+                &plane[start..start + size.x as usize]
+            })
+            .copied()
+            .collect();
+    }
+    // The source range covered by output sample `i` of `n`, at least one sample
+    let range = |i: u32, n: u32, min: u32, len: u32| {
+        let start = min + (i as u64 * len as u64 / n as u64) as u32;
+        let end = min + ((i as u64 + 1) * len as u64 / n as u64) as u32;
+        start..end.max(start + 1)
+    };
+    let mut data = Vec::with_capacity((size.x * size.y) as usize);
+    for y in 0..size.y {
+        let rows = range(y, size.y, region.min.y, source_size.y);
+        for x in 0..size.x {
+            let columns = range(x, size.x, region.min.x, source_size.x);
+            let count = rows.len() * columns.len();
+            let sum: usize = rows
+                .clone()
+                .map(|row| {
+                    let start = (row * stride) as usize;
+                    plane[start + columns.start as usize..start + columns.end as usize]
+                        .iter()
+                        .map(|&sample| sample as usize)
+                        .sum::<usize>()
+                })
+                .sum();
+            data.push((sum / count) as u8);
+        }
+    }
+    data
+}
+
+/// Set the alpha channel of BGRA `data` of `size` from the luma of the `region` of `p`.
+fn apply_alpha(data: &mut [u8], p: &dav1d::Picture, region: URect, size: UVec2) {
+    let region = region.intersect(URect::new(0, 0, p.width(), p.height()));
+    if region.is_empty() {
+        return;
+    }
+    let alpha = resample(
+        &p.plane(dav1d::PlanarImageComponent::Y),
+        p.stride(dav1d::PlanarImageComponent::Y),
+        region,
+        size,
+    );
+    let full_range = matches!(p.color_range(), dav1d::pixel::YUVRange::Full);
+    for (pixel, &alpha) in data.chunks_exact_mut(4).zip(&alpha) {
+        pixel[3] = if full_range {
+            alpha
+        } else {
+            // Expand limited range luma 16-235
+            ((alpha.saturating_sub(16) as u32 * 255 + 109) / 219).min(255) as u8
+        };
+    }
+}
+
 impl<R: Read + Seek + Send> crate::decodable::Decoder for Decoder<R> {
     fn width(&self) -> u32 {
-        self.demuxer.width() as u32
+        self.frame_size().0
     }
 
     fn height(&self) -> u32 {
-        self.demuxer.height() as u32
+        self.frame_size().1
+    }
+
+    fn display_aspect_ratio(&self) -> f32 {
+        match self.size {
+            Some(size) => {
+                let (width, height) =
+                    color_size(self.alpha_layout, size.render_width, size.render_height);
+                width as f32 / height as f32
+            }
+            None => self.width() as f32 / self.height() as f32,
+        }
     }
 
     fn timebase(&self) -> (u32, u32) {
         self.demuxer.timebase()
     }
 
+    fn frame_count(&self) -> Option<u64> {
+        self.frame_count
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    fn frame_duration(&self) -> Duration {
+        Decoder::frame_duration(self)
+    }
+
+    fn set_throttle(&mut self, throttle: DecodeThrottle) {
+        self.throttle = throttle;
+    }
+
+    fn set_range(&mut self, range: VideoRange) {
+        Decoder::set_range(self, range);
+    }
+
     async fn decode(
         &mut self,
         tx: async_channel::Sender<VideoFrame>,
@@ -209,3 +1081,234 @@ impl<R: Read + Seek + Send> crate::decodable::Decoder for Decoder<R> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, path::Path};
+
+    use super::*;
+
+    fn asset(name: &str) -> Vec<u8> {
+        std::fs::read(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("assets/av1")
+                .join(name),
+        )
+        .unwrap()
+    }
+
+    /// The header and the pts and data of each packet of an IVF file.
+    fn packets(ivf: &[u8]) -> (&[u8], Vec<(u64, &[u8])>) {
+        let header_len = u16::from_le_bytes([ivf[6], ivf[7]]) as usize;
+        let mut offset = header_len;
+        let mut packets = Vec::new();
+        while offset + 12 <= ivf.len() {
+            let len = u32::from_le_bytes(ivf[offset..offset + 4].try_into().unwrap()) as usize;
+            let pts = u64::from_le_bytes(ivf[offset + 4..offset + 12].try_into().unwrap());
+            packets.push((pts, &ivf[offset + 12..offset + 12 + len]));
+            offset += 12 + len;
+        }
+        (&ivf[..header_len], packets)
+    }
+
+    fn ivf<'a>(header: &[u8], packets: impl IntoIterator<Item = (u64, &'a [u8])>) -> Vec<u8> {
+        let mut ivf = header.to_vec();
+        for (pts, data) in packets {
+            ivf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            ivf.extend_from_slice(&pts.to_le_bytes());
+            ivf.extend_from_slice(data);
+        }
+        ivf
+    }
+
+    fn is_keyframe(data: &[u8]) -> bool {
+        av1::obu::Parser::default()
+            .parse_temporal_unit(data)
+            .is_ok_and(|header| header.is_some_and(|header| header.frame_type == FrameType::Key))
+    }
+
+    fn alpha_decoder(color: &[u8], alpha: &[u8]) -> Decoder<Cursor<Vec<u8>>> {
+        Decoder::new(Cursor::new(color.to_vec()), &DecodeSettings::default())
+            .unwrap()
+            .with_index(Arc::default())
+            .unwrap()
+            .with_alpha_stream(Cursor::new(alpha.to_vec()), Arc::default())
+            .unwrap()
+    }
+
+    /// Read packets with container `pts`, returning their timeline pts.
+    fn read_packets(timeline: &mut Timeline, pts: impl IntoIterator<Item = u64>) -> Vec<i64> {
+        pts.into_iter()
+            .map(|pts| {
+                let stream_pts = timeline.stream_pts(pts);
+                timeline.pts(stream_pts)
+            })
+            .collect()
+    }
+
+    /// Output the pictures with timeline `pts` from timeline pts `start` like
+    /// [`Decoder::next_picture`], skipping pictures only decoded as references.
+    /// Returns the timeline and stream pts of each picture output.
+    fn output_pictures(timeline: &mut Timeline, start: i64, pts: &[i64]) -> Vec<(i64, i64)> {
+        let mut next_pts = start;
+        pts.iter()
+            .filter_map(|&pts| {
+                if pts < next_pts {
+                    return None;
+                }
+                next_pts = pts + 1;
+                Some((pts, timeline.output_stream_pts(pts)))
+            })
+            .collect()
+    }
+
+    /// Check that the timeline pts of `pictures` increase by `frame_pts`.
+    fn assert_continuous(pictures: &[(i64, i64)], frame_pts: i64) {
+        for pair in pictures.windows(2) {
+            assert_eq!(
+                pair[1].0 - pair[0].0,
+                frame_pts,
+                "timeline pts of {pair:?} are not consecutive"
+            );
+        }
+    }
+
+    #[test]
+    fn timeline_loop() {
+        let mut timeline = Timeline::default();
+        let mut pts = Vec::new();
+        for pass in 0..3 {
+            if pass > 0 {
+                assert!(timeline.restart(0, timeline.end_pts()));
+            }
+            pts.extend(read_packets(&mut timeline, 100..110));
+            assert_eq!(timeline.end_pts(), 10);
+        }
+        // Without packets the loop would never end
+        assert!(timeline.restart(0, timeline.end_pts()));
+        assert!(!timeline.restart(0, timeline.end_pts()));
+
+        let pictures = output_pictures(&mut timeline, 0, &pts);
+        assert_eq!(pictures.len(), 30);
+        assert_continuous(&pictures, 1);
+        let stream_pts: Vec<i64> = pictures.iter().map(|&(_, pts)| pts).collect();
+        assert_eq!(stream_pts, (0..30).map(|i| i % 10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn timeline_loop_range() {
+        // Packets every 2 ticks with a single key frame, looping the range 4..12
+        let mut timeline = Timeline::default();
+        let (start, end) = (4, 12);
+        let mut pts = Vec::new();
+        for pass in 0..3 {
+            if pass > 0 {
+                assert!(timeline.restart(start, end));
+            }
+            // Decoding restarts at the key frame, the packet at the end restarts the loop
+            pts.extend(read_packets(&mut timeline, (0..end).step_by(2)));
+        }
+
+        let pictures = output_pictures(&mut timeline, start as i64, &pts);
+        assert_eq!(pictures.len(), 12);
+        assert_continuous(&pictures, 2);
+        let stream_pts: Vec<i64> = pictures.iter().map(|&(_, pts)| pts).collect();
+        assert_eq!(
+            stream_pts,
+            (0..12).map(|i| 4 + i % 4 * 2).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn timeline_seek_after_loop() {
+        // Seeking starts a new decoder at the key frame before the seek target,
+        // the first pts of the stream comes from the key frame index
+        let mut timeline = Timeline {
+            first_pts: Some(100),
+            ..default()
+        };
+        let mut pts = read_packets(&mut timeline, 106..110);
+        assert!(timeline.restart(0, timeline.end_pts()));
+        pts.extend(read_packets(&mut timeline, 100..110));
+
+        let pictures = output_pictures(&mut timeline, 7, &pts);
+        assert_continuous(&pictures, 1);
+        let stream_pts: Vec<i64> = pictures.iter().map(|&(_, pts)| pts).collect();
+        assert_eq!(
+            stream_pts,
+            [7, 8, 9].into_iter().chain(0..10).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn yuv_planes_only() {
+        let settings = DecodeSettings {
+            yuv_planes_only: true,
+            ..default()
+        };
+        let mut decoder =
+            Decoder::new(Cursor::new(asset("cosmos-laundromat.ivf")), &settings).unwrap();
+        let frame = decoder.next_frame().unwrap().unwrap();
+        assert!(frame.image.data.is_none());
+        let planes = frame.planes.unwrap();
+        // The coded size, at least the render size of the image
+        assert!(planes.width >= frame.image.width() && planes.height >= frame.image.height());
+        assert_eq!(planes.layout, PixelLayout::I420);
+        assert_eq!(planes.bit_depth, 8);
+        assert!(planes.y_stride >= planes.width);
+        assert!(planes.y.len() >= (planes.y_stride * planes.height) as usize);
+        assert!(planes.uv_stride >= planes.width.div_ceil(2));
+        assert!(planes.u.len() >= (planes.uv_stride * planes.height.div_ceil(2)) as usize);
+        assert_eq!(planes.u.len(), planes.v.len());
+    }
+
+    #[test]
+    fn alpha_stream_with_other_keyframes() {
+        let color = asset("cosmos-laundromat.ivf");
+        let (header, color_packets) = packets(&color);
+        let color_keyframes: Vec<usize> = (0..color_packets.len())
+            .filter(|&i| is_keyframe(color_packets[i].1))
+            .collect();
+        assert!(color_keyframes.len() > 2, "not enough key frames");
+        // Repeat the start of the stream, so the alpha stream has key frames
+        // after some key frames of the color stream
+        let repeat = color_keyframes[1] + 10;
+        let alpha = ivf(
+            header,
+            (0..color_packets.len()).map(|i| (color_packets[i].0, color_packets[i % repeat].1)),
+        );
+        let alpha_keyframes: Vec<usize> = (0..color_packets.len())
+            .filter(|&i| is_keyframe(color_packets[i % repeat].1))
+            .collect();
+        assert!(
+            color_keyframes
+                .iter()
+                .any(|keyframe| !alpha_keyframes.contains(keyframe))
+        );
+
+        let mut decoder = alpha_decoder(&color, &alpha);
+        let throttle = DecodeThrottle::default();
+        throttle.set(OffscreenPolicy::KeyframesOnly);
+        crate::decodable::Decoder::set_throttle(&mut decoder, throttle);
+        let mut keyframes = Vec::new();
+        while let Some(frame) = decoder.next_frame().unwrap() {
+            keyframes.push(frame);
+        }
+        assert_eq!(keyframes.len(), color_keyframes.len());
+
+        // Each key frame has the alpha of the same frame decoded at full rate
+        let mut decoder = alpha_decoder(&color, &alpha);
+        let mut keyframes = keyframes.into_iter().peekable();
+        while let Some(frame) = decoder.next_frame().unwrap() {
+            let Some(keyframe) = keyframes.next_if(|k| k.timestamp == frame.timestamp) else {
+                continue;
+            };
+            assert!(
+                keyframe.image.data == frame.image.data,
+                "key frame at {:?} does not match",
+                frame.timestamp
+            );
+        }
+        assert!(keyframes.peek().is_none(), "key frames not decoded");
+    }
+}
@@ -41,6 +41,11 @@ impl<R: Read + Seek + Send> Demuxer<R> {
         (self.header.timebase_num, self.header.timebase_den)
     }
 
+    /// The frame count of the header, which some muxers leave as zero.
+    pub fn frame_count(&self) -> u32 {
+        self.header.frame_count
+    }
+
     fn read_header(br: &mut ByteReader<R, LittleEndian>) -> io::Result<Header> {
         const TAG: &[u8] = b"DKIF";
         const CODEC: &[u8] = b"AV01";
@@ -88,8 +93,26 @@ impl<R: Read + Seek + Send> Demuxer<R> {
         Ok(Packet { data: buf, pts })
     }
 
+    /// Skip over the next packet, returns its pts.
+    pub fn skip_packet(&mut self) -> io::Result<u64> {
+        let len = self.reader.read::<u32>()?;
+        let pts = self.reader.read::<u64>()?;
+        self.reader.reader().seek(SeekFrom::Current(len as i64))?;
+        Ok(pts)
+    }
+
     pub fn reset(&mut self) -> io::Result<()> {
-        self.reader.reader().seek(SeekFrom::Start(HEADER_SIZE))?;
+        self.seek(HEADER_SIZE)
+    }
+
+    /// Byte offset of the next packet.
+    pub fn position(&mut self) -> io::Result<u64> {
+        self.reader.reader().stream_position()
+    }
+
+    /// Continue reading packets at byte `offset`, a previous [`Self::position`].
+    pub fn seek(&mut self, offset: u64) -> io::Result<()> {
+        self.reader.reader().seek(SeekFrom::Start(offset))?;
         Ok(())
     }
 }
@@ -0,0 +1,1511 @@
+// Parses the AV1 OBU metadata that dav1d does not expose.
+// See https://aomediacodec.github.io/av1-spec/
+//
+// dav1d parses the same headers, but the `dav1d` crate keeps the frame header of a
+// `Picture` private. `film_grain_params()` is the last element of the uncompressed header,
+// so frame headers are parsed up to it. Fields that do not change how many bits follow
+// are skipped rather than decoded.
+
+use bevy::log::debug;
+use bitstream_io::{BigEndian, BitRead, BitReader};
+use std::io;
+
+use crate::frame_info::{FilmGrainParams, FrameType};
+
+const OBU_SEQUENCE_HEADER: u8 = 1;
+const OBU_FRAME_HEADER: u8 = 3;
+const OBU_FRAME: u8 = 6;
+
+const NUM_REF_FRAMES: usize = 8;
+const REFS_PER_FRAME: usize = 7;
+const ALL_FRAMES: u8 = 0xff;
+const SELECT_SCREEN_CONTENT_TOOLS: u8 = 2;
+const SELECT_INTEGER_MV: u8 = 2;
+const PRIMARY_REF_NONE: usize = 7;
+const GOLDEN_FRAME: usize = 3;
+const BWDREF_FRAME: usize = 4;
+const ALTREF2_FRAME: usize = 5;
+const ALTREF_FRAME: usize = 6;
+const SUPERRES_NUM: u32 = 8;
+const SUPERRES_DENOM_MIN: u32 = 9;
+const MAX_SEGMENTS: usize = 8;
+const SEG_LVL_MAX: usize = 8;
+/// `(bits, signed, max)` of each segmentation feature value.
+const SEGMENTATION_FEATURES: [(u32, bool, i32); SEG_LVL_MAX] = [
+    (8, true, 255),
+    (6, true, 63),
+    (6, true, 63),
+    (6, true, 63),
+    (6, true, 63),
+    (3, false, 7),
+    (0, false, 0),
+    (0, false, 0),
+];
+const MAX_TILE_WIDTH: u32 = 4096;
+const MAX_TILE_AREA: u32 = 4096 * 2304;
+const MAX_TILE_COLS: u32 = 64;
+const MAX_TILE_ROWS: u32 = 64;
+const IDENTITY: u8 = 0;
+const TRANSLATION: u8 = 1;
+const ROTZOOM: u8 = 2;
+const AFFINE: u8 = 3;
+const GM_ABS_TRANS_ONLY_BITS: u32 = 9;
+const GM_ABS_TRANS_BITS: u32 = 12;
+const GM_ABS_ALPHA_BITS: u32 = 12;
+
+type Reader<'a> = BitReader<&'a [u8], BigEndian>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColorConfig {
+    pub color_primaries: u8,
+    pub transfer_characteristics: u8,
+    pub matrix_coefficients: u8,
+    mono_chrome: bool,
+    subsampling_x: bool,
+    subsampling_y: bool,
+    separate_uv_delta_q: bool,
+}
+
+#[derive(Debug, Clone)]
+struct OperatingPoint {
+    idc: u16,
+    decoder_model_present: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct SequenceHeader {
+    reduced_still_picture_header: bool,
+    /// `(buffer_removal_time_length, frame_presentation_time_length)` if decoder model info is present.
+    decoder_model_info: Option<(u32, u32)>,
+    equal_picture_interval: bool,
+    operating_points: Vec<OperatingPoint>,
+    frame_width_bits: u32,
+    frame_height_bits: u32,
+    max_frame_width: u32,
+    max_frame_height: u32,
+    /// `(frame_id_length, delta_frame_id_length)` if frame id numbers are present.
+    frame_id_length: Option<(u32, u32)>,
+    order_hint_bits: u32,
+    seq_force_screen_content_tools: u8,
+    seq_force_integer_mv: u8,
+    use_128x128_superblock: bool,
+    enable_warped_motion: bool,
+    enable_ref_frame_mvs: bool,
+    enable_superres: bool,
+    enable_cdef: bool,
+    enable_restoration: bool,
+    pub color_config: ColorConfig,
+    film_grain_params_present: bool,
+}
+
+/// The dimensions of a frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FrameSize {
+    /// The frame width, after any superres upscaling.
+    pub upscaled_width: u32,
+    pub frame_height: u32,
+    /// The intended display width, which may differ from the frame width.
+    pub render_width: u32,
+    /// The intended display height, which may differ from the frame height.
+    pub render_height: u32,
+}
+
+/// The header of a shown frame.
+#[derive(Debug, Clone)]
+pub struct FrameHeader {
+    pub frame_type: FrameType,
+    pub size: FrameSize,
+    pub color_config: ColorConfig,
+    pub film_grain: Option<FilmGrainParams>,
+}
+
+/// The state of a reference frame slot.
+#[derive(Debug, Default, Clone)]
+struct RefFrame {
+    frame_type: FrameType,
+    size: FrameSize,
+    order_hint: u32,
+    /// The alternative quantizer segmentation feature value of each segment, if enabled.
+    segment_alt_q: [Option<i32>; MAX_SEGMENTS],
+    film_grain: Option<FilmGrainParams>,
+}
+
+/// Fields of the uncompressed header needed to parse the remainder of the header.
+struct FrameState {
+    frame_type: FrameType,
+    frame_is_intra: bool,
+    show_frame: bool,
+    showable_frame: bool,
+    error_resilient_mode: bool,
+    disable_cdf_update: bool,
+    order_hint: u32,
+    primary_ref_frame: Option<usize>,
+    ref_frame_idx: [usize; REFS_PER_FRAME],
+    size: FrameSize,
+    /// The frame width before superres upscaling.
+    frame_width: u32,
+    allow_intrabc: bool,
+    allow_high_precision_mv: bool,
+}
+
+/// Reference frame state parsed from the remainder of the uncompressed header.
+#[derive(Default)]
+struct FrameTail {
+    segment_alt_q: [Option<i32>; MAX_SEGMENTS],
+    film_grain: Option<FilmGrainParams>,
+}
+
+/// Parses temporal units, tracking the sequence header and reference frame state.
+#[derive(Debug, Default)]
+pub struct Parser {
+    sequence_header: Option<SequenceHeader>,
+    ref_frames: [RefFrame; NUM_REF_FRAMES],
+}
+
+impl Parser {
+    /// Parse the OBUs of a temporal unit, returning the header of the frame it shows.
+    pub fn parse_temporal_unit(&mut self, mut data: &[u8]) -> io::Result<Option<FrameHeader>> {
+        let mut shown = None;
+        while let Some(&header) = data.first() {
+            let obu_type = (header >> 3) & 0xf;
+            let extension_flag = header & 0x4 != 0;
+            let has_size_field = header & 0x2 != 0;
+            let mut offset = 1;
+            let (mut temporal_id, mut spatial_id) = (0, 0);
+            if extension_flag {
+                let extension = *data.get(offset).ok_or_else(truncated)?;
+                temporal_id = extension >> 5;
+                spatial_id = (extension >> 3) & 0x3;
+                offset += 1;
+            }
+            let size = if has_size_field {
+                let (size, len) = leb128(data.get(offset..).ok_or_else(truncated)?)?;
+                offset += len;
+                usize::try_from(size).map_err(|_| truncated())?
+            } else {
+                data.len() - offset
+            };
+            let end = offset.checked_add(size).ok_or_else(truncated)?;
+            let payload = data.get(offset..end).ok_or_else(truncated)?;
+            data = &data[end..];
+
+            match obu_type {
+                OBU_SEQUENCE_HEADER => {
+                    self.sequence_header = Some(SequenceHeader::parse(payload)?);
+                }
+                OBU_FRAME_HEADER | OBU_FRAME => {
+                    if let Some(frame_header) =
+                        self.parse_frame_header(payload, temporal_id, spatial_id)?
+                    {
+                        shown = Some(frame_header);
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(shown)
+    }
+
+    /// Parse an uncompressed frame header.
+    /// Returns the header if the frame is shown.
+    fn parse_frame_header(
+        &mut self,
+        payload: &[u8],
+        temporal_id: u8,
+        spatial_id: u8,
+    ) -> io::Result<Option<FrameHeader>> {
+        let Some(seq) = &self.sequence_header else {
+            return Ok(None);
+        };
+        let mut r = BitReader::endian(payload, BigEndian);
+
+        let show_existing_frame = !seq.reduced_still_picture_header && r.read_bit()?;
+        if show_existing_frame {
+            let frame_to_show_map_idx = r.read::<3, u8>()? as usize;
+            // Remaining fields are not needed
+            let frame = self.ref_frames[frame_to_show_map_idx].clone();
+            let header = FrameHeader {
+                frame_type: frame.frame_type,
+                size: frame.size,
+                color_config: seq.color_config,
+                film_grain: frame.film_grain.clone(),
+            };
+            if frame.frame_type == FrameType::Key {
+                // Showing a key frame refreshes all reference frames
+                self.ref_frames = std::array::from_fn(|_| frame.clone());
+            }
+            return Ok(Some(header));
+        }
+
+        let (frame_type, show_frame) = if seq.reduced_still_picture_header {
+            (FrameType::Key, true)
+        } else {
+            let frame_type = match r.read::<2, u8>()? {
+                0 => FrameType::Key,
+                1 => FrameType::Inter,
+                2 => FrameType::IntraOnly,
+                _ => FrameType::Switch,
+            };
+            (frame_type, r.read_bit()?)
+        };
+        let frame_is_intra = matches!(frame_type, FrameType::Key | FrameType::IntraOnly);
+        if show_frame
+            && let Some((_, frame_presentation_time_length)) = seq.decoder_model_info
+            && !seq.equal_picture_interval
+        {
+            read_bits(&mut r, frame_presentation_time_length)?;
+        }
+        let showable_frame = if show_frame {
+            frame_type != FrameType::Key
+        } else {
+            r.read_bit()?
+        };
+        let error_resilient_mode =
+            if frame_type == FrameType::Switch || (frame_type == FrameType::Key && show_frame) {
+                true
+            } else {
+                r.read_bit()?
+            };
+        let disable_cdf_update = r.read_bit()?;
+        let allow_screen_content_tools =
+            if seq.seq_force_screen_content_tools == SELECT_SCREEN_CONTENT_TOOLS {
+                r.read_bit()?
+            } else {
+                seq.seq_force_screen_content_tools != 0
+            };
+        let mut force_integer_mv = if !allow_screen_content_tools {
+            false
+        } else if seq.seq_force_integer_mv == SELECT_INTEGER_MV {
+            r.read_bit()?
+        } else {
+            seq.seq_force_integer_mv != 0
+        };
+        if frame_is_intra {
+            force_integer_mv = true;
+        }
+        if let Some((frame_id_length, _)) = seq.frame_id_length {
+            let _current_frame_id = read_bits(&mut r, frame_id_length)?;
+        }
+        let frame_size_override_flag = if frame_type == FrameType::Switch {
+            true
+        } else if seq.reduced_still_picture_header {
+            false
+        } else {
+            r.read_bit()?
+        };
+        let order_hint = read_bits(&mut r, seq.order_hint_bits)?;
+        let primary_ref_frame = if frame_is_intra || error_resilient_mode {
+            None
+        } else {
+            Some(r.read::<3, u8>()? as usize).filter(|&i| i != PRIMARY_REF_NONE)
+        };
+        if let Some((buffer_removal_time_length, _)) = seq.decoder_model_info {
+            let buffer_removal_time_present_flag = r.read_bit()?;
+            if buffer_removal_time_present_flag {
+                for op in seq.operating_points.iter() {
+                    if !op.decoder_model_present {
+                        continue;
+                    }
+                    let in_temporal_layer = (op.idc >> temporal_id) & 1 != 0;
+                    let in_spatial_layer = (op.idc >> (spatial_id + 8)) & 1 != 0;
+                    if op.idc == 0 || (in_temporal_layer && in_spatial_layer) {
+                        read_bits(&mut r, buffer_removal_time_length)?;
+                    }
+                }
+            }
+        }
+        let refresh_frame_flags =
+            if frame_type == FrameType::Switch || (frame_type == FrameType::Key && show_frame) {
+                ALL_FRAMES
+            } else {
+                r.read::<8, u8>()?
+            };
+        if (!frame_is_intra || refresh_frame_flags != ALL_FRAMES)
+            && error_resilient_mode
+            && seq.order_hint_bits > 0
+        {
+            for ref_frame in self.ref_frames.iter_mut() {
+                let ref_order_hint = read_bits(&mut r, seq.order_hint_bits)?;
+                if ref_order_hint != ref_frame.order_hint {
+                    *ref_frame = RefFrame {
+                        order_hint: ref_order_hint,
+                        ..RefFrame::default()
+                    };
+                }
+            }
+        }
+
+        let mut ref_frame_idx = [0; REFS_PER_FRAME];
+        let mut allow_intrabc = false;
+        let mut allow_high_precision_mv = false;
+        let (size, frame_width) = if frame_is_intra {
+            let (size, frame_width) = FrameSize::parse(&mut r, seq, frame_size_override_flag)?;
+            allow_intrabc =
+                allow_screen_content_tools && size.upscaled_width == frame_width && r.read_bit()?;
+            (size, frame_width)
+        } else {
+            let frame_refs_short_signaling = seq.order_hint_bits > 0 && r.read_bit()?;
+            if frame_refs_short_signaling {
+                let last_frame_idx = r.read::<3, u8>()? as usize;
+                let gold_frame_idx = r.read::<3, u8>()? as usize;
+                ref_frame_idx = seq.set_frame_refs(
+                    last_frame_idx,
+                    gold_frame_idx,
+                    order_hint,
+                    &self.ref_frames,
+                );
+            }
+            for idx in ref_frame_idx.iter_mut() {
+                if !frame_refs_short_signaling {
+                    *idx = r.read::<3, u8>()? as usize;
+                }
+                if let Some((_, delta_frame_id_length)) = seq.frame_id_length {
+                    let _delta_frame_id_minus_1 = read_bits(&mut r, delta_frame_id_length)?;
+                }
+            }
+            let (size, frame_width) = if frame_size_override_flag && !error_resilient_mode {
+                FrameSize::parse_with_refs(&mut r, seq, &ref_frame_idx, &self.ref_frames)?
+            } else {
+                FrameSize::parse(&mut r, seq, frame_size_override_flag)?
+            };
+            allow_high_precision_mv = !force_integer_mv && r.read_bit()?;
+            let is_filter_switchable = r.read_bit()?;
+            if !is_filter_switchable {
+                let _interpolation_filter = r.read::<2, u8>()?;
+            }
+            let _is_motion_mode_switchable = r.read_bit()?;
+            if !error_resilient_mode && seq.enable_ref_frame_mvs {
+                let _use_ref_frame_mvs = r.read_bit()?;
+            }
+            (size, frame_width)
+        };
+
+        // The remainder of the header is only needed for film grain parameters
+        let frame = FrameState {
+            frame_type,
+            frame_is_intra,
+            show_frame,
+            showable_frame,
+            error_resilient_mode,
+            disable_cdf_update,
+            order_hint,
+            primary_ref_frame,
+            ref_frame_idx,
+            size,
+            frame_width,
+            allow_intrabc,
+            allow_high_precision_mv,
+        };
+        let tail = if seq.film_grain_params_present {
+            seq.parse_frame_tail(&mut r, &frame, &self.ref_frames)
+                .unwrap_or_else(|e| {
+                    debug!("Failed to parse AV1 film grain parameters: {e}");
+                    FrameTail::default()
+                })
+        } else {
+            FrameTail::default()
+        };
+
+        let ref_frame = RefFrame {
+            frame_type,
+            size,
+            order_hint,
+            segment_alt_q: tail.segment_alt_q,
+            film_grain: tail.film_grain,
+        };
+        for (i, slot) in self.ref_frames.iter_mut().enumerate() {
+            if refresh_frame_flags & (1 << i) != 0 {
+                *slot = ref_frame.clone();
+            }
+        }
+
+        Ok(show_frame.then_some(FrameHeader {
+            frame_type,
+            size,
+            color_config: seq.color_config,
+            film_grain: ref_frame.film_grain,
+        }))
+    }
+}
+
+impl FrameSize {
+    /// Parse `frame_size()` and `render_size()`, also returns the frame width before upscaling.
+    fn parse(
+        r: &mut Reader,
+        seq: &SequenceHeader,
+        frame_size_override_flag: bool,
+    ) -> io::Result<(Self, u32)> {
+        let (upscaled_width, frame_height) = if frame_size_override_flag {
+            (
+                read_bits(r, seq.frame_width_bits)? + 1,
+                read_bits(r, seq.frame_height_bits)? + 1,
+            )
+        } else {
+            (seq.max_frame_width, seq.max_frame_height)
+        };
+        let frame_width = Self::parse_superres_params(r, seq, upscaled_width)?;
+        let render_and_frame_size_different = r.read_bit()?;
+        let (render_width, render_height) = if render_and_frame_size_different {
+            (r.read::<16, u32>()? + 1, r.read::<16, u32>()? + 1)
+        } else {
+            (upscaled_width, frame_height)
+        };
+        let size = Self {
+            upscaled_width,
+            frame_height,
+            render_width,
+            render_height,
+        };
+        Ok((size, frame_width))
+    }
+
+    /// Parse `frame_size_with_refs()`, also returns the frame width before upscaling.
+    fn parse_with_refs(
+        r: &mut Reader,
+        seq: &SequenceHeader,
+        ref_frame_idx: &[usize; REFS_PER_FRAME],
+        ref_frames: &[RefFrame; NUM_REF_FRAMES],
+    ) -> io::Result<(Self, u32)> {
+        for &idx in ref_frame_idx {
+            let found_ref = r.read_bit()?;
+            if found_ref {
+                let size = ref_frames[idx].size;
+                let frame_width = Self::parse_superres_params(r, seq, size.upscaled_width)?;
+                return Ok((size, frame_width));
+            }
+        }
+        Self::parse(r, seq, true)
+    }
+
+    /// Parse `superres_params()`, returns the frame width before upscaling.
+    fn parse_superres_params(
+        r: &mut Reader,
+        seq: &SequenceHeader,
+        upscaled_width: u32,
+    ) -> io::Result<u32> {
+        if seq.enable_superres && r.read_bit()? {
+            let coded_denom = r.read::<3, u32>()?;
+            let denom = coded_denom + SUPERRES_DENOM_MIN;
+            Ok((upscaled_width * SUPERRES_NUM + denom / 2) / denom)
+        } else {
+            Ok(upscaled_width)
+        }
+    }
+}
+
+impl SequenceHeader {
+    /// Parse the uncompressed header after `use_ref_frame_mvs`, through `film_grain_params()`.
+    fn parse_frame_tail(
+        &self,
+        r: &mut Reader,
+        f: &FrameState,
+        ref_frames: &[RefFrame; NUM_REF_FRAMES],
+    ) -> io::Result<FrameTail> {
+        let prev_frame = f
+            .primary_ref_frame
+            .map(|primary_ref_frame| &ref_frames[f.ref_frame_idx[primary_ref_frame]]);
+        let cc = &self.color_config;
+
+        if !(self.reduced_still_picture_header || f.disable_cdf_update) {
+            let _disable_frame_end_update_cdf = r.read_bit()?;
+        }
+        self.parse_tile_info(r, f)?;
+        let (base_q_idx, zero_delta_q) = cc.parse_quantization_params(r)?;
+        let segment_alt_q = parse_segmentation_params(r, prev_frame)?;
+        let delta_q_present = base_q_idx > 0 && r.read_bit()?;
+        if delta_q_present {
+            let _delta_q_res = r.read::<2, u8>()?;
+            let delta_lf_present = !f.allow_intrabc && r.read_bit()?;
+            if delta_lf_present {
+                let _delta_lf_res = r.read::<2, u8>()?;
+                let _delta_lf_multi = r.read_bit()?;
+            }
+        }
+        let coded_lossless = zero_delta_q
+            && segment_alt_q
+                .iter()
+                .all(|alt_q| (base_q_idx + alt_q.unwrap_or(0)).clamp(0, 255) == 0);
+        let all_lossless = coded_lossless && f.frame_width == f.size.upscaled_width;
+
+        // loop_filter_params()
+        if !(coded_lossless || f.allow_intrabc) {
+            let loop_filter_level_0 = r.read::<6, u8>()?;
+            let loop_filter_level_1 = r.read::<6, u8>()?;
+            if !cc.mono_chrome && (loop_filter_level_0 != 0 || loop_filter_level_1 != 0) {
+                let _loop_filter_level_2 = r.read::<6, u8>()?;
+                let _loop_filter_level_3 = r.read::<6, u8>()?;
+            }
+            let _loop_filter_sharpness = r.read::<3, u8>()?;
+            let loop_filter_delta_enabled = r.read_bit()?;
+            if loop_filter_delta_enabled && r.read_bit()? {
+                // Reference and mode deltas
+                for _ in 0..NUM_REF_FRAMES + 2 {
+                    if r.read_bit()? {
+                        su(r, 7)?;
+                    }
+                }
+            }
+        }
+        // cdef_params()
+        if !(coded_lossless || f.allow_intrabc || !self.enable_cdef) {
+            let _cdef_damping_minus_3 = r.read::<2, u8>()?;
+            let cdef_bits = r.read::<2, u32>()?;
+            for _ in 0..1 << cdef_bits {
+                let _cdef_y_strength = r.read::<6, u8>()?;
+                if !cc.mono_chrome {
+                    let _cdef_uv_strength = r.read::<6, u8>()?;
+                }
+            }
+        }
+        // lr_params()
+        if !(all_lossless || f.allow_intrabc || !self.enable_restoration) {
+            let num_planes = if cc.mono_chrome { 1 } else { 3 };
+            let mut uses_lr = false;
+            let mut uses_chroma_lr = false;
+            for plane in 0..num_planes {
+                let lr_type = r.read::<2, u8>()?;
+                if lr_type != 0 {
+                    uses_lr = true;
+                    uses_chroma_lr |= plane > 0;
+                }
+            }
+            if uses_lr {
+                let lr_unit_shift = r.read_bit()?;
+                if !self.use_128x128_superblock && lr_unit_shift {
+                    let _lr_unit_extra_shift = r.read_bit()?;
+                }
+                if cc.subsampling_x && cc.subsampling_y && uses_chroma_lr {
+                    let _lr_uv_shift = r.read_bit()?;
+                }
+            }
+        }
+        if !coded_lossless {
+            let _tx_mode_select = r.read_bit()?;
+        }
+        let reference_select = !f.frame_is_intra && r.read_bit()?;
+        if self.skip_mode_allowed(f, reference_select, ref_frames) {
+            let _skip_mode_present = r.read_bit()?;
+        }
+        if !(f.frame_is_intra || f.error_resilient_mode || !self.enable_warped_motion) {
+            let _allow_warped_motion = r.read_bit()?;
+        }
+        let _reduced_tx_set = r.read_bit()?;
+        skip_global_motion_params(r, f)?;
+        let film_grain = self.parse_film_grain_params(r, f, ref_frames)?;
+
+        Ok(FrameTail {
+            segment_alt_q,
+            film_grain,
+        })
+    }
+
+    /// Parse `tile_info()`.
+    fn parse_tile_info(&self, r: &mut Reader, f: &FrameState) -> io::Result<()> {
+        let mi_cols = 2 * f.frame_width.div_ceil(8);
+        let mi_rows = 2 * f.size.frame_height.div_ceil(8);
+        let (sb_cols, sb_rows, sb_shift) = if self.use_128x128_superblock {
+            (mi_cols.div_ceil(32), mi_rows.div_ceil(32), 5)
+        } else {
+            (mi_cols.div_ceil(16), mi_rows.div_ceil(16), 4)
+        };
+        let sb_size = sb_shift + 2;
+        let max_tile_width_sb = MAX_TILE_WIDTH >> sb_size;
+        let mut max_tile_area_sb = MAX_TILE_AREA >> (2 * sb_size);
+        let min_log2_tile_cols = tile_log2(max_tile_width_sb, sb_cols);
+        let max_log2_tile_cols = tile_log2(1, sb_cols.min(MAX_TILE_COLS));
+        let max_log2_tile_rows = tile_log2(1, sb_rows.min(MAX_TILE_ROWS));
+        let min_log2_tiles = min_log2_tile_cols.max(tile_log2(max_tile_area_sb, sb_rows * sb_cols));
+
+        let uniform_tile_spacing_flag = r.read_bit()?;
+        let (tile_cols_log2, tile_rows_log2) = if uniform_tile_spacing_flag {
+            let mut tile_cols_log2 = min_log2_tile_cols;
+            while tile_cols_log2 < max_log2_tile_cols && r.read_bit()? {
+                tile_cols_log2 += 1;
+            }
+            let mut tile_rows_log2 = min_log2_tiles.saturating_sub(tile_cols_log2);
+            while tile_rows_log2 < max_log2_tile_rows && r.read_bit()? {
+                tile_rows_log2 += 1;
+            }
+            (tile_cols_log2, tile_rows_log2)
+        } else {
+            let mut widest_tile_sb = 0;
+            let mut start_sb = 0;
+            let mut tile_cols = 0;
+            while start_sb < sb_cols {
+                let max_width = (sb_cols - start_sb).min(max_tile_width_sb);
+                let size_sb = ns(r, max_width)? + 1;
+                widest_tile_sb = widest_tile_sb.max(size_sb);
+                start_sb += size_sb;
+                tile_cols += 1;
+            }
+            if min_log2_tiles > 0 {
+                max_tile_area_sb = (sb_rows * sb_cols) >> (min_log2_tiles + 1);
+            } else {
+                max_tile_area_sb = sb_rows * sb_cols;
+            }
+            let max_tile_height_sb = (max_tile_area_sb / widest_tile_sb).max(1);
+            let mut start_sb = 0;
+            let mut tile_rows = 0;
+            while start_sb < sb_rows {
+                let max_height = (sb_rows - start_sb).min(max_tile_height_sb);
+                start_sb += ns(r, max_height)? + 1;
+                tile_rows += 1;
+            }
+            (tile_log2(1, tile_cols), tile_log2(1, tile_rows))
+        };
+        if tile_cols_log2 > 0 || tile_rows_log2 > 0 {
+            let _context_update_tile_id = read_bits(r, tile_rows_log2 + tile_cols_log2)?;
+            let _tile_size_bytes_minus_1 = r.read::<2, u8>()?;
+        }
+        Ok(())
+    }
+
+    /// Whether `skip_mode_present` is signaled, see `skip_mode_params()`.
+    fn skip_mode_allowed(
+        &self,
+        f: &FrameState,
+        reference_select: bool,
+        ref_frames: &[RefFrame; NUM_REF_FRAMES],
+    ) -> bool {
+        if f.frame_is_intra || !reference_select || self.order_hint_bits == 0 {
+            return false;
+        }
+        let mut forward_hint = None;
+        let mut backward_hint = None;
+        for &idx in &f.ref_frame_idx {
+            let ref_hint = ref_frames[idx].order_hint;
+            let dist = self.relative_dist(ref_hint, f.order_hint);
+            if dist < 0 && forward_hint.is_none_or(|hint| self.relative_dist(ref_hint, hint) > 0) {
+                forward_hint = Some(ref_hint);
+            } else if dist > 0
+                && backward_hint.is_none_or(|hint| self.relative_dist(ref_hint, hint) < 0)
+            {
+                backward_hint = Some(ref_hint);
+            }
+        }
+        let Some(forward_hint) = forward_hint else {
+            return false;
+        };
+        // Otherwise a second forward reference is required
+        backward_hint.is_some()
+            || f.ref_frame_idx
+                .iter()
+                .any(|&idx| self.relative_dist(ref_frames[idx].order_hint, forward_hint) < 0)
+    }
+
+    /// Derive the references of a frame using short signaling, see `set_frame_refs()`.
+    fn set_frame_refs(
+        &self,
+        last_frame_idx: usize,
+        gold_frame_idx: usize,
+        order_hint: u32,
+        ref_frames: &[RefFrame; NUM_REF_FRAMES],
+    ) -> [usize; REFS_PER_FRAME] {
+        let mut ref_frame_idx = [None; REFS_PER_FRAME];
+        ref_frame_idx[0] = Some(last_frame_idx);
+        ref_frame_idx[GOLDEN_FRAME] = Some(gold_frame_idx);
+        let mut used_frame = [false; NUM_REF_FRAMES];
+        used_frame[last_frame_idx] = true;
+        used_frame[gold_frame_idx] = true;
+
+        let cur_frame_hint = 1 << (self.order_hint_bits - 1);
+        let shifted_order_hints: [i32; NUM_REF_FRAMES] = std::array::from_fn(|i| {
+            cur_frame_hint + self.relative_dist(ref_frames[i].order_hint, order_hint)
+        });
+        // Find the latest or earliest unused backward or forward reference
+        let find = |used_frame: &[bool; NUM_REF_FRAMES], backward: bool, latest: bool| {
+            let mut found: Option<(usize, i32)> = None;
+            for (i, &hint) in shifted_order_hints.iter().enumerate() {
+                if used_frame[i] || (hint >= cur_frame_hint) != backward {
+                    continue;
+                }
+                let better =
+                    found.is_none_or(|(_, best)| if latest { hint >= best } else { hint < best });
+                if better {
+                    found = Some((i, hint));
+                }
+            }
+            found.map(|(i, _)| i)
+        };
+
+        for (ref_frame, latest) in [
+            (ALTREF_FRAME, true),
+            (BWDREF_FRAME, false),
+            (ALTREF2_FRAME, false),
+        ] {
+            if let Some(i) = find(&used_frame, true, latest) {
+                ref_frame_idx[ref_frame] = Some(i);
+                used_frame[i] = true;
+            }
+        }
+        for ref_frame in [1, 2, BWDREF_FRAME, ALTREF2_FRAME, ALTREF_FRAME] {
+            if ref_frame_idx[ref_frame].is_none()
+                && let Some(i) = find(&used_frame, false, true)
+            {
+                ref_frame_idx[ref_frame] = Some(i);
+                used_frame[i] = true;
+            }
+        }
+        // Remaining references use the earliest frame
+        let earliest = (0..NUM_REF_FRAMES)
+            .min_by_key(|&i| shifted_order_hints[i])
+            .unwrap_or(0);
+        ref_frame_idx.map(|idx| idx.unwrap_or(earliest))
+    }
+
+    /// The signed distance between two order hints, see `get_relative_dist()`.
+    fn relative_dist(&self, a: u32, b: u32) -> i32 {
+        if self.order_hint_bits == 0 {
+            return 0;
+        }
+        let diff = a as i32 - b as i32;
+        let m = 1 << (self.order_hint_bits - 1);
+        (diff & (m - 1)) - (diff & m)
+    }
+
+    /// Parse `film_grain_params()`.
+    fn parse_film_grain_params(
+        &self,
+        r: &mut Reader,
+        f: &FrameState,
+        ref_frames: &[RefFrame; NUM_REF_FRAMES],
+    ) -> io::Result<Option<FilmGrainParams>> {
+        if !self.film_grain_params_present || (!f.show_frame && !f.showable_frame) {
+            return Ok(None);
+        }
+        let apply_grain = r.read_bit()?;
+        if !apply_grain {
+            return Ok(None);
+        }
+        let random_seed = r.read::<16, u16>()?;
+        let update_grain = f.frame_type != FrameType::Inter || r.read_bit()?;
+        if !update_grain {
+            let film_grain_params_ref_idx = r.read::<3, u8>()? as usize;
+            return Ok(ref_frames[film_grain_params_ref_idx]
+                .film_grain
+                .clone()
+                .map(|film_grain| FilmGrainParams {
+                    random_seed,
+                    ..film_grain
+                }));
+        }
+
+        let cc = &self.color_config;
+        let num_y_points = r.read::<4, u8>()?;
+        let y_points = read_scaling_points(r, num_y_points)?;
+        let chroma_scaling_from_luma = !cc.mono_chrome && r.read_bit()?;
+        let (cb_points, cr_points) = if cc.mono_chrome
+            || chroma_scaling_from_luma
+            || (cc.subsampling_x && cc.subsampling_y && num_y_points == 0)
+        {
+            (Vec::new(), Vec::new())
+        } else {
+            let num_cb_points = r.read::<4, u8>()?;
+            let cb_points = read_scaling_points(r, num_cb_points)?;
+            let num_cr_points = r.read::<4, u8>()?;
+            let cr_points = read_scaling_points(r, num_cr_points)?;
+            (cb_points, cr_points)
+        };
+        let scaling_shift = r.read::<2, u8>()? + 8;
+        let ar_coeff_lag = r.read::<2, u8>()?;
+        let num_pos_luma = 2 * ar_coeff_lag as usize * (ar_coeff_lag as usize + 1);
+        let (ar_coeffs_y, num_pos_chroma) = if num_y_points > 0 {
+            (read_ar_coeffs(r, num_pos_luma)?, num_pos_luma + 1)
+        } else {
+            (Vec::new(), num_pos_luma)
+        };
+        let ar_coeffs_cb = if chroma_scaling_from_luma || !cb_points.is_empty() {
+            read_ar_coeffs(r, num_pos_chroma)?
+        } else {
+            Vec::new()
+        };
+        let ar_coeffs_cr = if chroma_scaling_from_luma || !cr_points.is_empty() {
+            read_ar_coeffs(r, num_pos_chroma)?
+        } else {
+            Vec::new()
+        };
+        let ar_coeff_shift = r.read::<2, u8>()? + 6;
+        let grain_scale_shift = r.read::<2, u8>()?;
+        let (cb_mult, cb_luma_mult, cb_offset) = if !cb_points.is_empty() {
+            (r.read::<8, u8>()?, r.read::<8, u8>()?, r.read::<9, u16>()?)
+        } else {
+            (0, 0, 0)
+        };
+        let (cr_mult, cr_luma_mult, cr_offset) = if !cr_points.is_empty() {
+            (r.read::<8, u8>()?, r.read::<8, u8>()?, r.read::<9, u16>()?)
+        } else {
+            (0, 0, 0)
+        };
+        let overlap_flag = r.read_bit()?;
+        let clip_to_restricted_range = r.read_bit()?;
+
+        Ok(Some(FilmGrainParams {
+            random_seed,
+            y_points,
+            chroma_scaling_from_luma,
+            cb_points,
+            cr_points,
+            scaling_shift,
+            ar_coeff_lag,
+            ar_coeffs_y,
+            ar_coeffs_cb,
+            ar_coeffs_cr,
+            ar_coeff_shift,
+            grain_scale_shift,
+            cb_mult,
+            cb_luma_mult,
+            cb_offset,
+            cr_mult,
+            cr_luma_mult,
+            cr_offset,
+            overlap_flag,
+            clip_to_restricted_range,
+        }))
+    }
+
+    fn parse(payload: &[u8]) -> io::Result<Self> {
+        let mut r = BitReader::endian(payload, BigEndian);
+        let seq_profile = r.read::<3, u8>()?;
+        let _still_picture = r.read_bit()?;
+        let reduced_still_picture_header = r.read_bit()?;
+
+        let mut decoder_model_info = None;
+        let mut equal_picture_interval = false;
+        let mut operating_points = Vec::new();
+        if reduced_still_picture_header {
+            let _seq_level_idx = r.read::<5, u8>()?;
+            operating_points.push(OperatingPoint {
+                idc: 0,
+                decoder_model_present: false,
+            });
+        } else {
+            let timing_info_present_flag = r.read_bit()?;
+            let mut buffer_delay_length = 0;
+            if timing_info_present_flag {
+                let _num_units_in_display_tick = r.read::<32, u32>()?;
+                let _time_scale = r.read::<32, u32>()?;
+                equal_picture_interval = r.read_bit()?;
+                if equal_picture_interval {
+                    let _num_ticks_per_picture_minus_1 = uvlc(&mut r)?;
+                }
+                let decoder_model_info_present_flag = r.read_bit()?;
+                if decoder_model_info_present_flag {
+                    buffer_delay_length = r.read::<5, u32>()? + 1;
+                    let _num_units_in_decoding_tick = r.read::<32, u32>()?;
+                    let buffer_removal_time_length = r.read::<5, u32>()? + 1;
+                    let frame_presentation_time_length = r.read::<5, u32>()? + 1;
+                    decoder_model_info =
+                        Some((buffer_removal_time_length, frame_presentation_time_length));
+                }
+            }
+            let initial_display_delay_present_flag = r.read_bit()?;
+            let operating_points_cnt = r.read::<5, u8>()? + 1;
+            for _ in 0..operating_points_cnt {
+                let idc = r.read::<12, u16>()?;
+                let seq_level_idx = r.read::<5, u8>()?;
+                if seq_level_idx > 7 {
+                    let _seq_tier = r.read_bit()?;
+                }
+                let mut decoder_model_present = false;
+                if decoder_model_info.is_some() {
+                    decoder_model_present = r.read_bit()?;
+                    if decoder_model_present {
+                        let _decoder_buffer_delay = read_bits(&mut r, buffer_delay_length)?;
+                        let _encoder_buffer_delay = read_bits(&mut r, buffer_delay_length)?;
+                        let _low_delay_mode_flag = r.read_bit()?;
+                    }
+                }
+                if initial_display_delay_present_flag && r.read_bit()? {
+                    let _initial_display_delay_minus_1 = r.read::<4, u8>()?;
+                }
+                operating_points.push(OperatingPoint {
+                    idc,
+                    decoder_model_present,
+                });
+            }
+        }
+
+        let frame_width_bits = r.read::<4, u32>()? + 1;
+        let frame_height_bits = r.read::<4, u32>()? + 1;
+        let max_frame_width = read_bits(&mut r, frame_width_bits)? + 1;
+        let max_frame_height = read_bits(&mut r, frame_height_bits)? + 1;
+        let frame_id_numbers_present_flag = !reduced_still_picture_header && r.read_bit()?;
+        let mut frame_id_length = None;
+        if frame_id_numbers_present_flag {
+            let delta_frame_id_length_minus_2 = r.read::<4, u32>()?;
+            let additional_frame_id_length_minus_1 = r.read::<3, u32>()?;
+            frame_id_length = Some((
+                additional_frame_id_length_minus_1 + delta_frame_id_length_minus_2 + 3,
+                delta_frame_id_length_minus_2 + 2,
+            ));
+        }
+        let use_128x128_superblock = r.read_bit()?;
+        let _enable_filter_intra = r.read_bit()?;
+        let _enable_intra_edge_filter = r.read_bit()?;
+
+        let mut seq_force_screen_content_tools = SELECT_SCREEN_CONTENT_TOOLS;
+        let mut seq_force_integer_mv = SELECT_INTEGER_MV;
+        let mut order_hint_bits = 0;
+        let mut enable_warped_motion = false;
+        let mut enable_ref_frame_mvs = false;
+        if !reduced_still_picture_header {
+            let _enable_interintra_compound = r.read_bit()?;
+            let _enable_masked_compound = r.read_bit()?;
+            enable_warped_motion = r.read_bit()?;
+            let _enable_dual_filter = r.read_bit()?;
+            let enable_order_hint = r.read_bit()?;
+            if enable_order_hint {
+                let _enable_jnt_comp = r.read_bit()?;
+                enable_ref_frame_mvs = r.read_bit()?;
+            }
+            let seq_choose_screen_content_tools = r.read_bit()?;
+            if !seq_choose_screen_content_tools {
+                seq_force_screen_content_tools = r.read::<1, u8>()?;
+            }
+            if seq_force_screen_content_tools > 0 {
+                let seq_choose_integer_mv = r.read_bit()?;
+                if !seq_choose_integer_mv {
+                    seq_force_integer_mv = r.read::<1, u8>()?;
+                }
+            }
+            if enable_order_hint {
+                order_hint_bits = r.read::<3, u32>()? + 1;
+            }
+        }
+        let enable_superres = r.read_bit()?;
+        let enable_cdef = r.read_bit()?;
+        let enable_restoration = r.read_bit()?;
+        let color_config = ColorConfig::parse(&mut r, seq_profile)?;
+        let film_grain_params_present = r.read_bit()?;
+
+        Ok(Self {
+            reduced_still_picture_header,
+            decoder_model_info,
+            equal_picture_interval,
+            operating_points,
+            frame_width_bits,
+            frame_height_bits,
+            max_frame_width,
+            max_frame_height,
+            frame_id_length,
+            order_hint_bits,
+            seq_force_screen_content_tools,
+            seq_force_integer_mv,
+            use_128x128_superblock,
+            enable_warped_motion,
+            enable_ref_frame_mvs,
+            enable_superres,
+            enable_cdef,
+            enable_restoration,
+            color_config,
+            film_grain_params_present,
+        })
+    }
+}
+
+impl ColorConfig {
+    fn parse(r: &mut Reader, seq_profile: u8) -> io::Result<Self> {
+        const CP_BT_709: u8 = 1;
+        const CP_UNSPECIFIED: u8 = 2;
+        const TC_UNSPECIFIED: u8 = 2;
+        const TC_SRGB: u8 = 13;
+        const MC_IDENTITY: u8 = 0;
+        const MC_UNSPECIFIED: u8 = 2;
+
+        let high_bitdepth = r.read_bit()?;
+        let twelve_bit = seq_profile == 2 && high_bitdepth && r.read_bit()?;
+        let mono_chrome = seq_profile != 1 && r.read_bit()?;
+        let color_description_present_flag = r.read_bit()?;
+        let (color_primaries, transfer_characteristics, matrix_coefficients) =
+            if color_description_present_flag {
+                (r.read::<8, u8>()?, r.read::<8, u8>()?, r.read::<8, u8>()?)
+            } else {
+                (CP_UNSPECIFIED, TC_UNSPECIFIED, MC_UNSPECIFIED)
+            };
+        let mut color_config = Self {
+            color_primaries,
+            transfer_characteristics,
+            matrix_coefficients,
+            mono_chrome,
+            subsampling_x: true,
+            subsampling_y: true,
+            separate_uv_delta_q: false,
+        };
+        if mono_chrome {
+            let _color_range = r.read_bit()?;
+            return Ok(color_config);
+        }
+        if color_primaries == CP_BT_709
+            && transfer_characteristics == TC_SRGB
+            && matrix_coefficients == MC_IDENTITY
+        {
+            color_config.subsampling_x = false;
+            color_config.subsampling_y = false;
+        } else {
+            let _color_range = r.read_bit()?;
+            match seq_profile {
+                0 => (),
+                1 => {
+                    color_config.subsampling_x = false;
+                    color_config.subsampling_y = false;
+                }
+                _ if twelve_bit => {
+                    color_config.subsampling_x = r.read_bit()?;
+                    color_config.subsampling_y = color_config.subsampling_x && r.read_bit()?;
+                }
+                _ => color_config.subsampling_y = false,
+            }
+            if color_config.subsampling_x && color_config.subsampling_y {
+                let _chroma_sample_position = r.read::<2, u8>()?;
+            }
+        }
+        color_config.separate_uv_delta_q = r.read_bit()?;
+        Ok(color_config)
+    }
+
+    /// Parse `quantization_params()`, returns `base_q_idx`,
+    /// and whether all quantizer deltas are zero.
+    fn parse_quantization_params(&self, r: &mut Reader) -> io::Result<(i32, bool)> {
+        let base_q_idx = r.read::<8, u8>()? as i32;
+        let mut zero_delta_q = read_delta_q(r)? == 0;
+        if !self.mono_chrome {
+            let diff_uv_delta = self.separate_uv_delta_q && r.read_bit()?;
+            let planes = if diff_uv_delta { 2 } else { 1 };
+            // DC and AC deltas of each chroma plane
+            for _ in 0..planes * 2 {
+                zero_delta_q &= read_delta_q(r)? == 0;
+            }
+        }
+        let using_qmatrix = r.read_bit()?;
+        if using_qmatrix {
+            let _qm_y = r.read::<4, u8>()?;
+            let _qm_u = r.read::<4, u8>()?;
+            if self.separate_uv_delta_q {
+                let _qm_v = r.read::<4, u8>()?;
+            }
+        }
+        Ok((base_q_idx, zero_delta_q))
+    }
+}
+
+/// Parse `segmentation_params()`, returns the alternative quantizer of each segment.
+fn parse_segmentation_params(
+    r: &mut Reader,
+    prev_frame: Option<&RefFrame>,
+) -> io::Result<[Option<i32>; MAX_SEGMENTS]> {
+    const SEG_LVL_ALT_Q: usize = 0;
+
+    let segmentation_enabled = r.read_bit()?;
+    if !segmentation_enabled {
+        return Ok([None; MAX_SEGMENTS]);
+    }
+    let segmentation_update_data = match prev_frame {
+        None => true,
+        Some(_) => {
+            let segmentation_update_map = r.read_bit()?;
+            if segmentation_update_map {
+                let _segmentation_temporal_update = r.read_bit()?;
+            }
+            r.read_bit()?
+        }
+    };
+    if !segmentation_update_data && let Some(prev_frame) = prev_frame {
+        return Ok(prev_frame.segment_alt_q);
+    }
+    let mut segment_alt_q = [None; MAX_SEGMENTS];
+    for alt_q in segment_alt_q.iter_mut() {
+        for (feature, &(bits, signed, limit)) in SEGMENTATION_FEATURES.iter().enumerate() {
+            let feature_enabled = r.read_bit()?;
+            if !feature_enabled {
+                continue;
+            }
+            let value = if signed {
+                su(r, 1 + bits)?.clamp(-limit, limit)
+            } else {
+                (read_bits(r, bits)? as i32).clamp(0, limit)
+            };
+            if feature == SEG_LVL_ALT_Q {
+                *alt_q = Some(value);
+            }
+        }
+    }
+    Ok(segment_alt_q)
+}
+
+/// Skip `global_motion_params()`.
+/// The parameters of the reference frame only change the decoded values,
+/// not the number of bits read, so they are not tracked.
+fn skip_global_motion_params(r: &mut Reader, f: &FrameState) -> io::Result<()> {
+    if f.frame_is_intra {
+        return Ok(());
+    }
+    for _ in 0..REFS_PER_FRAME {
+        let is_global = r.read_bit()?;
+        let gm_type = if !is_global {
+            IDENTITY
+        } else if r.read_bit()? {
+            ROTZOOM
+        } else if r.read_bit()? {
+            TRANSLATION
+        } else {
+            AFFINE
+        };
+        let params: &[usize] = match gm_type {
+            IDENTITY => &[],
+            TRANSLATION => &[0, 1],
+            ROTZOOM => &[2, 3, 0, 1],
+            _ => &[2, 3, 4, 5, 0, 1],
+        };
+        for &idx in params {
+            let abs_bits = if idx >= 2 {
+                GM_ABS_ALPHA_BITS
+            } else if gm_type == TRANSLATION {
+                GM_ABS_TRANS_ONLY_BITS - !f.allow_high_precision_mv as u32
+            } else {
+                GM_ABS_TRANS_BITS
+            };
+            // `decode_signed_subexp_with_ref()` from `-mx` to `mx` inclusive
+            decode_subexp(r, (2 << abs_bits) + 1)?;
+        }
+    }
+    Ok(())
+}
+
+fn decode_subexp(r: &mut Reader, num_syms: i32) -> io::Result<i32> {
+    const K: u32 = 3;
+    let mut i = 0;
+    let mut mk = 0;
+    loop {
+        let b2 = if i > 0 { K + i - 1 } else { K };
+        let a = 1 << b2;
+        if num_syms <= mk + 3 * a {
+            return Ok(ns(r, (num_syms - mk) as u32)? as i32 + mk);
+        }
+        let subexp_more_bits = r.read_bit()?;
+        if !subexp_more_bits {
+            return Ok(read_bits(r, b2)? as i32 + mk);
+        }
+        i += 1;
+        mk += a;
+    }
+}
+
+fn read_delta_q(r: &mut Reader) -> io::Result<i32> {
+    let delta_coded = r.read_bit()?;
+    if delta_coded { su(r, 1 + 6) } else { Ok(0) }
+}
+
+/// Read `(value, scaling)` points of a film grain scaling function.
+fn read_scaling_points(r: &mut Reader, num_points: u8) -> io::Result<Vec<(u8, u8)>> {
+    (0..num_points)
+        .map(|_| Ok((r.read::<8, u8>()?, r.read::<8, u8>()?)))
+        .collect()
+}
+
+/// Read film grain auto-regressive coefficients, coded with an offset of 128.
+fn read_ar_coeffs(r: &mut Reader, num_pos: usize) -> io::Result<Vec<i8>> {
+    (0..num_pos)
+        .map(|_| Ok((r.read::<8, u8>()? as i16 - 128) as i8))
+        .collect()
+}
+
+/// The smallest `k` such that `blk_size << k` is at least `target`.
+fn tile_log2(blk_size: u32, target: u32) -> u32 {
+    let mut k = 0;
+    while (blk_size << k) < target {
+        k += 1;
+    }
+    k
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "Truncated OBU")
+}
+
+fn leb128(data: &[u8]) -> io::Result<(u64, usize)> {
+    let mut value = 0u64;
+    for (i, byte) in data.iter().take(8).enumerate() {
+        value |= ((byte & 0x7f) as u64) << (i * 7);
+        if byte & 0x80 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+    Err(truncated())
+}
+
+fn uvlc(r: &mut Reader) -> io::Result<u32> {
+    let mut leading_zeros = 0;
+    while !r.read_bit()? {
+        leading_zeros += 1;
+        if leading_zeros >= 32 {
+            return Ok(u32::MAX);
+        }
+    }
+    Ok(read_bits(r, leading_zeros)? + ((1 << leading_zeros) - 1))
+}
+
+fn read_bits(r: &mut Reader, bits: u32) -> io::Result<u32> {
+    if bits == 0 { Ok(0) } else { r.read_var(bits) }
+}
+
+/// Read a signed integer of `n` bits.
+fn su(r: &mut Reader, n: u32) -> io::Result<i32> {
+    let value = read_bits(r, n)? as i32;
+    let sign_mask = 1 << (n - 1);
+    Ok(if value & sign_mask != 0 {
+        value - 2 * sign_mask
+    } else {
+        value
+    })
+}
+
+/// Read a non-symmetric unsigned integer less than `n`.
+fn ns(r: &mut Reader, n: u32) -> io::Result<u32> {
+    let w = n.checked_ilog2().ok_or_else(truncated)? + 1;
+    let m = (1 << w) - n;
+    let v = read_bits(r, w - 1)?;
+    if v < m {
+        return Ok(v);
+    }
+    let extra_bit = r.read_bit()? as u32;
+    Ok((v << 1) - m + extra_bit)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, mem, path::Path, ptr};
+
+    use dav1d_sys::*;
+
+    use super::*;
+    use crate::av1::ivf::Demuxer;
+
+    /// A dav1d decoder exposing the frame header of each picture,
+    /// which the `dav1d` crate does not.
+    struct RawDecoder(*mut Dav1dContext);
+
+    impl RawDecoder {
+        fn new() -> Self {
+            unsafe {
+                let mut settings: Dav1dSettings = mem::zeroed();
+                dav1d_default_settings(&mut settings);
+                settings.n_threads = 1;
+                settings.max_frame_delay = 1;
+                let mut context = ptr::null_mut();
+                assert_eq!(dav1d_open(&mut context, &settings), 0);
+                Self(context)
+            }
+        }
+
+        /// Send a temporal unit, calling `f` for each picture output.
+        fn send(&mut self, packet: &[u8], f: &mut impl FnMut(&Dav1dPicture, &Dav1dFrameHeader)) {
+            unsafe {
+                let mut data: Dav1dData = mem::zeroed();
+                let buf = dav1d_data_create(&mut data, packet.len());
+                ptr::copy_nonoverlapping(packet.as_ptr(), buf, packet.len());
+                while data.sz > 0 {
+                    match dav1d_send_data(self.0, &mut data) {
+                        0 | DAV1D_ERR_AGAIN => (),
+                        e => panic!("dav1d_send_data failed: {e}"),
+                    }
+                    self.drain(f);
+                }
+            }
+        }
+
+        /// Output all decoded pictures.
+        fn drain(&mut self, f: &mut impl FnMut(&Dav1dPicture, &Dav1dFrameHeader)) {
+            unsafe {
+                loop {
+                    let mut picture: Dav1dPicture = mem::zeroed();
+                    match dav1d_get_picture(self.0, &mut picture) {
+                        0 => {
+                            f(&picture, &*picture.frame_hdr);
+                            dav1d_picture_unref(&mut picture);
+                        }
+                        DAV1D_ERR_AGAIN => return,
+                        e => panic!("dav1d_get_picture failed: {e}"),
+                    }
+                }
+            }
+        }
+    }
+
+    impl Drop for RawDecoder {
+        fn drop(&mut self) {
+            unsafe { dav1d_close(&mut self.0) };
+        }
+    }
+
+    fn frame_type(frame_type: Dav1dFrameType) -> FrameType {
+        match frame_type {
+            DAV1D_FRAME_TYPE_KEY => FrameType::Key,
+            DAV1D_FRAME_TYPE_INTER => FrameType::Inter,
+            DAV1D_FRAME_TYPE_INTRA => FrameType::IntraOnly,
+            DAV1D_FRAME_TYPE_SWITCH => FrameType::Switch,
+            _ => panic!("invalid frame type {frame_type}"),
+        }
+    }
+
+    /// dav1d film grain data as [`FilmGrainParams`], undoing the offsets dav1d subtracts.
+    fn film_grain(data: &Dav1dFilmGrainData) -> FilmGrainParams {
+        fn points(points: &[[u8; 2]], n: i32) -> Vec<(u8, u8)> {
+            points[..n as usize]
+                .iter()
+                .map(|&[value, scaling]| (value, scaling))
+                .collect()
+        }
+        let lag = data.ar_coeff_lag as usize;
+        let num_pos_luma = 2 * lag * (lag + 1);
+        let num_pos_chroma = num_pos_luma + (data.num_y_points > 0) as usize;
+        let chroma = |pl: usize| {
+            let coeffs = if data.chroma_scaling_from_luma != 0 || data.num_uv_points[pl] > 0 {
+                data.ar_coeffs_uv[pl][..num_pos_chroma].to_vec()
+            } else {
+                Vec::new()
+            };
+            let (mult, luma_mult, offset) = if data.num_uv_points[pl] > 0 {
+                (
+                    (data.uv_mult[pl] + 128) as u8,
+                    (data.uv_luma_mult[pl] + 128) as u8,
+                    (data.uv_offset[pl] + 256) as u16,
+                )
+            } else {
+                (0, 0, 0)
+            };
+            (
+                points(&data.uv_points[pl], data.num_uv_points[pl]),
+                coeffs,
+                mult,
+                luma_mult,
+                offset,
+            )
+        };
+        let (cb_points, ar_coeffs_cb, cb_mult, cb_luma_mult, cb_offset) = chroma(0);
+        let (cr_points, ar_coeffs_cr, cr_mult, cr_luma_mult, cr_offset) = chroma(1);
+        FilmGrainParams {
+            random_seed: data.seed as u16,
+            y_points: points(&data.y_points, data.num_y_points),
+            chroma_scaling_from_luma: data.chroma_scaling_from_luma != 0,
+            cb_points,
+            cr_points,
+            scaling_shift: data.scaling_shift as u8,
+            ar_coeff_lag: lag as u8,
+            ar_coeffs_y: if data.num_y_points > 0 {
+                data.ar_coeffs_y[..num_pos_luma].to_vec()
+            } else {
+                Vec::new()
+            },
+            ar_coeffs_cb,
+            ar_coeffs_cr,
+            ar_coeff_shift: data.ar_coeff_shift as u8,
+            grain_scale_shift: data.grain_scale_shift as u8,
+            cb_mult,
+            cb_luma_mult,
+            cb_offset,
+            cr_mult,
+            cr_luma_mult,
+            cr_offset,
+            overlap_flag: data.overlap_flag != 0,
+            clip_to_restricted_range: data.clip_to_restricted_range != 0,
+        }
+    }
+
+    /// The fields of a dav1d picture and its frame header compared with [`FrameHeader`].
+    #[derive(Debug, PartialEq)]
+    struct Frame {
+        frame_type: FrameType,
+        size: FrameSize,
+        film_grain: Option<FilmGrainParams>,
+    }
+
+    impl Frame {
+        fn new(picture: &Dav1dPicture, header: &Dav1dFrameHeader) -> Self {
+            // Pictures of `show_existing_frame` carry the header of the frame they show
+            assert!(header.show_frame != 0 || header.showable_frame != 0);
+            assert_eq!((picture.p.w, picture.p.h), (header.width[1], header.height));
+            Self {
+                frame_type: frame_type(header.frame_type),
+                size: FrameSize {
+                    upscaled_width: header.width[1] as u32,
+                    frame_height: header.height as u32,
+                    render_width: header.render_width as u32,
+                    render_height: header.render_height as u32,
+                },
+                film_grain: (header.film_grain.present != 0)
+                    .then(|| film_grain(&header.film_grain.data)),
+            }
+        }
+    }
+
+    impl From<FrameHeader> for Frame {
+        fn from(header: FrameHeader) -> Self {
+            Self {
+                frame_type: header.frame_type,
+                size: header.size,
+                film_grain: header.film_grain,
+            }
+        }
+    }
+
+    #[test]
+    fn primitives() {
+        let reader = |data: &'static [u8]| BitReader::endian(data, BigEndian);
+        assert_eq!(leb128(&[0xe5, 0x8e, 0x26]).unwrap(), (624_485, 3));
+        assert!(leb128(&[0x80]).is_err());
+        assert_eq!(uvlc(&mut reader(&[0b0010_1000])).unwrap(), 4);
+        assert_eq!(su(&mut reader(&[0b1111_1110]), 7).unwrap(), -1);
+        assert_eq!(su(&mut reader(&[0b0111_1110]), 7).unwrap(), 63);
+        // Values below `(1 << w) - n` use one bit less
+        assert_eq!(ns(&mut reader(&[0b1000_0000]), 5).unwrap(), 2);
+        assert_eq!(ns(&mut reader(&[0b1110_0000]), 5).unwrap(), 4);
+        // A global motion alpha parameter that fits in the first 3 bits
+        let mut r = reader(&[0b0101_1010]);
+        assert_eq!(
+            decode_subexp(&mut r, (2 << GM_ABS_ALPHA_BITS) + 1).unwrap(),
+            5
+        );
+        assert_eq!(r.read::<4, u8>().unwrap(), 0b1010);
+    }
+
+    /// Parse every frame of the bundled videos and check the headers against dav1d.
+    #[test]
+    fn frame_headers_match_dav1d() {
+        let assets = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/av1");
+        let mut videos = 0;
+        for entry in std::fs::read_dir(assets).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|extension| extension != "ivf") {
+                continue;
+            }
+            let mut demuxer = Demuxer::new(File::open(&path).unwrap()).unwrap();
+            let mut parser = Parser::default();
+            let mut decoder = RawDecoder::new();
+            let mut parsed = Vec::new();
+            let mut decoded = Vec::new();
+            let mut output = |picture: &Dav1dPicture, header: &Dav1dFrameHeader| {
+                decoded.push(Frame::new(picture, header));
+            };
+            loop {
+                let packet = match demuxer.read_packet() {
+                    Ok(packet) => packet,
+                    Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                    Err(e) => panic!("{path:?}: {e}"),
+                };
+                if let Some(header) = parser.parse_temporal_unit(&packet.data).unwrap() {
+                    parsed.push(Frame::from(header));
+                }
+                decoder.send(&packet.data, &mut output);
+            }
+            decoder.drain(&mut output);
+
+            assert!(!decoded.is_empty(), "{path:?} has no frames");
+            assert_eq!(parsed.len(), decoded.len(), "{path:?} frame count");
+            for (index, (parsed, decoded)) in parsed.iter().zip(&decoded).enumerate() {
+                assert_eq!(parsed, decoded, "{path:?} frame {index}");
+            }
+            videos += 1;
+        }
+        assert!(videos > 0, "no videos in assets/av1");
+    }
+}
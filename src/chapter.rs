@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+/// A titled section of a video.
+///
+/// Chapters are loaded from a sidecar file, see [`VideoLoaderSettings::chapters_path`][crate::VideoLoaderSettings::chapters_path].
+/// Navigate them with [`VideoSink::next_chapter`][crate::VideoSink::next_chapter] and
+/// [`VideoSink::previous_chapter`][crate::VideoSink::previous_chapter].
+/// A chapter ends where the next one starts.
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct Chapter {
+    /// The title of the chapter.
+    pub title: String,
+    /// The start of the chapter from the start of the video.
+    pub start: Duration,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ChapterFile {
+    title: String,
+    /// Start time in seconds.
+    start: f64,
+}
+
+/// The chapters of a sidecar file, sorted by start time.
+pub(crate) fn chapters(file: Vec<ChapterFile>) -> std::io::Result<Vec<Chapter>> {
+    let mut chapters = file
+        .into_iter()
+        .map(|chapter| {
+            Ok(Chapter {
+                title: chapter.title,
+                start: Duration::try_from_secs_f64(chapter.start).map_err(std::io::Error::other)?,
+            })
+        })
+        .collect::<std::io::Result<Vec<_>>>()?;
+    chapters.sort_by_key(|chapter| chapter.start);
+    Ok(chapters)
+}
@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+
+/// Chroma key (green screen) settings, making pixels close to a key color transparent.
+///
+/// Keying is applied when frames are converted, see [`DecodeSettings::chroma_key`][crate::DecodeSettings::chroma_key].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChromaKey {
+    /// The key color to make transparent.
+    pub color: Color,
+    /// How close the chroma of a pixel must be to the key color to be transparent, from `0.0` to `1.0`.
+    pub similarity: f32,
+    /// Width of the soft edge between transparent and opaque pixels, from `0.0` to `1.0`.
+    pub smoothness: f32,
+    /// Width of the key color spill suppression, from `0.0` to `1.0`.
+    /// Opaque pixels with chroma close to the key color are desaturated.
+    pub spill: f32,
+}
+
+impl Default for ChromaKey {
+    fn default() -> Self {
+        Self {
+            color: Color::srgb(0.0, 1.0, 0.0),
+            similarity: 0.4,
+            smoothness: 0.08,
+            spill: 0.1,
+        }
+    }
+}
+
+impl ChromaKey {
+    /// Apply the chroma key to BGRA pixel `data`.
+    pub(crate) fn apply(&self, data: &mut [u8]) {
+        let key = self.color.to_srgba();
+        let key_chroma = chroma(key.red, key.green, key.blue);
+        let smoothness = self.smoothness.max(f32::EPSILON);
+        let spill = self.spill.max(f32::EPSILON);
+        for pixel in data.chunks_exact_mut(4) {
+            let [b, g, r, a] = [pixel[0], pixel[1], pixel[2], pixel[3]].map(|c| c as f32 / 255.0);
+            let mask = chroma(r, g, b).distance(key_chroma) - self.similarity;
+            let alpha = a * falloff(mask / smoothness);
+            // Desaturate toward luma to suppress spill
+            let spill_mask = falloff(mask / spill);
+            let luma = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+            let [b, g, r] = [b, g, r].map(|c| luma + (c - luma) * spill_mask);
+            pixel.copy_from_slice(&[b, g, r, alpha].map(|c| (c * 255.0).round() as u8));
+        }
+    }
+}
+
+/// BT.709 chroma (Cb, Cr) of an RGB color.
+fn chroma(r: f32, g: f32, b: f32) -> Vec2 {
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    Vec2::new((b - y) / 1.8556, (r - y) / 1.5748)
+}
+
+fn falloff(x: f32) -> f32 {
+    let x = x.clamp(0.0, 1.0);
+    x * x.sqrt()
+}
@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::video_sink::VideoSink;
+
+/// A clock that plays several videos in lockstep, e.g. a video wall or a multi-angle replay.
+///
+/// Spawn it on its own entity, then add [`SyncToClock`] next to each [`VideoPlayer`][crate::VideoPlayer]
+/// to synchronize. The clock starts once every synchronized video has decoded its first frame,
+/// and waits for them again after seeking, so decode latency does not make videos drift apart.
+/// Looping videos of the same duration stay in lockstep across loops.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_av1::{PlaybackMode, SyncToClock, VideoClock, VideoPlayer};
+/// fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+///     let clock = commands.spawn(VideoClock::default()).id();
+///     for angle in ["av1/replay-left.ivf", "av1/replay-right.ivf"] {
+///         commands.spawn((
+///             VideoPlayer::new(asset_server.load(angle), PlaybackMode::Loop),
+///             SyncToClock(clock),
+///         ));
+///     }
+/// }
+///
+/// fn toggle_pause(keys: Res<ButtonInput<KeyCode>>, mut clocks: Query<&mut VideoClock>) {
+///     if keys.just_pressed(KeyCode::Space) {
+///         for mut clock in &mut clocks {
+///             clock.toggle();
+///         }
+///     }
+/// }
+/// ```
+#[derive(Component, Debug, Clone, Default)]
+pub struct VideoClock {
+    elapsed: Duration,
+    paused: bool,
+    /// Every synchronized video was ready, and the clock started.
+    started: bool,
+    /// Waiting for the synchronized videos to be ready.
+    waiting: bool,
+    seek: Option<Duration>,
+}
+
+/// Synchronizes the [`VideoSink`] of this entity to the [`VideoClock`] of the given entity.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncToClock(pub Entity);
+
+impl VideoClock {
+    /// Time since playback started, from the start of the [`VideoRange`][crate::VideoRange]
+    /// of each video.
+    pub fn elapsed(&self) -> Duration {
+        self.seek.unwrap_or(self.elapsed)
+    }
+
+    /// Pause all synchronized videos.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resume all synchronized videos.
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Pause if playing, resume if paused.
+    pub fn toggle(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// Whether the synchronized videos are paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Seek all synchronized videos to `time` from the start of their
+    /// [`VideoRange`][crate::VideoRange], see [`VideoSink::seek`].
+    pub fn seek(&mut self, time: Duration) {
+        self.seek = Some(time);
+    }
+
+    pub(crate) fn update_clocks(
+        mut clocks: Query<(Entity, &mut VideoClock)>,
+        mut synced: Query<(&SyncToClock, Option<&mut VideoSink>)>,
+        time: Res<Time>,
+    ) {
+        for (entity, mut clock) in &mut clocks {
+            let mut sinks = synced
+                .iter_mut()
+                .filter(|(sync, _)| sync.0 == entity)
+                .map(|(_, sink)| sink);
+            if let Some(seek) = clock.seek.take() {
+                for mut sink in sinks.flatten() {
+                    let range_start = sink.range_start();
+                    sink.seek(range_start + seek);
+                    sink.set_clock_time(Some(seek));
+                }
+                clock.elapsed = seek;
+                clock.waiting = true;
+                continue;
+            }
+            if !clock.started || clock.waiting {
+                let started = clock.started;
+                // Videos still loading hold back the start, but not seeks
+                let mut ready = true;
+                let mut any = false;
+                for sink in &mut sinks {
+                    match sink {
+                        Some(mut sink) => {
+                            any = true;
+                            ready &= sink.is_ready();
+                        }
+                        None => ready &= started,
+                    }
+                }
+                if ready && any {
+                    clock.started = true;
+                    clock.waiting = false;
+                }
+            } else if !clock.paused {
+                clock.elapsed += time.delta();
+            }
+            let elapsed = clock.elapsed;
+            for mut sink in synced
+                .iter_mut()
+                .filter(|(sync, _)| sync.0 == entity)
+                .filter_map(|(_, sink)| sink)
+            {
+                sink.set_clock_time(Some(elapsed));
+            }
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    window::{MonitorSelection, PrimaryWindow, WindowMode},
+};
+
+use crate::video_sink::VideoSink;
+
+/// Adds standard playback controls to [`VideoControls`] UI nodes.
+pub struct VideoControlsPlugin;
+
+impl Plugin for VideoControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                VideoControls::spawn_controls,
+                VideoControls::press_buttons,
+                VideoControls::scrub,
+                VideoControls::update_controls,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// A UI node with playback controls for the video of an entity:
+/// a play/pause button, a seek bar that can be dragged to scrub,
+/// the position and duration, and a fullscreen toggle for the primary window.
+///
+/// Requires [`VideoControlsPlugin`]. The controls are spawned as children of this node,
+/// position and size it with its [`Node`]. Videos have no audio, so there is no volume control.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_av1::{PlaybackMode, VideoControls, VideoPlayer};
+/// fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+///     let video = commands
+///         .spawn(VideoPlayer::new(asset_server.load("av1/tutorial.ivf"), PlaybackMode::Remove))
+///         .id();
+///     commands.spawn((
+///         VideoControls::new(video),
+///         Node {
+///             position_type: PositionType::Absolute,
+///             bottom: Val::Px(0.0),
+///             width: Val::Percent(100.0),
+///             ..default()
+///         },
+///     ));
+/// }
+/// ```
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+#[require(Node)]
+pub struct VideoControls {
+    /// The entity of the [`VideoPlayer`][crate::VideoPlayer] to control.
+    pub video: Entity,
+}
+
+#[derive(Component)]
+struct PlayPauseButton(Entity);
+
+#[derive(Component)]
+struct FullscreenButton;
+
+#[derive(Component)]
+struct SeekBar {
+    video: Entity,
+    /// The position under the cursor while scrubbing.
+    scrubbed: Option<Duration>,
+    /// The last position seeked to while scrubbing.
+    seeked: Option<Duration>,
+}
+
+#[derive(Component)]
+struct SeekBarFill(Entity);
+
+#[derive(Component)]
+struct TimeDisplay(Entity);
+
+const BUTTON_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.6);
+const TRACK_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.3);
+const FILL_COLOR: Color = Color::WHITE;
+
+impl VideoControls {
+    /// Creates new [`VideoControls`] for the video of `video`.
+    pub fn new(video: Entity) -> Self {
+        Self { video }
+    }
+
+    fn spawn_controls(
+        query: Query<(Entity, &VideoControls), Added<VideoControls>>,
+        mut commands: Commands,
+    ) {
+        for (entity, controls) in &query {
+            let video = controls.video;
+            let font = TextFont {
+                font_size: 16.0,
+                ..default()
+            };
+            commands
+                .entity(entity)
+                .insert(BackgroundColor(BUTTON_COLOR))
+                .with_children(|parent| {
+                    parent.spawn((
+                        PlayPauseButton(video),
+                        Button,
+                        Text::new("Pause"),
+                        font.clone(),
+                        Node {
+                            padding: UiRect::all(Val::Px(8.0)),
+                            ..default()
+                        },
+                    ));
+                    parent
+                        .spawn((
+                            SeekBar {
+                                video,
+                                scrubbed: None,
+                                seeked: None,
+                            },
+                            Button,
+                            BackgroundColor(TRACK_COLOR),
+                            Node {
+                                flex_grow: 1.0,
+                                height: Val::Px(8.0),
+                                align_self: AlignSelf::Center,
+                                ..default()
+                            },
+                        ))
+                        .with_child((
+                            SeekBarFill(video),
+                            BackgroundColor(FILL_COLOR),
+                            Node {
+                                width: Val::Percent(0.0),
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                        ));
+                    parent.spawn((
+                        TimeDisplay(video),
+                        Text::new("0:00"),
+                        font.clone(),
+                        Node {
+                            padding: UiRect::all(Val::Px(8.0)),
+                            ..default()
+                        },
+                    ));
+                    parent.spawn((
+                        FullscreenButton,
+                        Button,
+                        Text::new("Fullscreen"),
+                        font,
+                        Node {
+                            padding: UiRect::all(Val::Px(8.0)),
+                            ..default()
+                        },
+                    ));
+                });
+        }
+    }
+
+    fn press_buttons(
+        play_buttons: Query<(&Interaction, &PlayPauseButton), Changed<Interaction>>,
+        fullscreen_buttons: Query<&Interaction, (Changed<Interaction>, With<FullscreenButton>)>,
+        mut sinks: Query<&mut VideoSink>,
+        mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    ) {
+        for (interaction, button) in &play_buttons {
+            if *interaction == Interaction::Pressed
+                && let Ok(mut sink) = sinks.get_mut(button.0)
+            {
+                sink.toggle_pause();
+            }
+        }
+        for interaction in &fullscreen_buttons {
+            if *interaction == Interaction::Pressed
+                && let Ok(mut window) = windows.single_mut()
+            {
+                window.mode = match window.mode {
+                    WindowMode::Windowed => {
+                        WindowMode::BorderlessFullscreen(MonitorSelection::Current)
+                    }
+                    _ => WindowMode::Windowed,
+                };
+            }
+        }
+    }
+
+    /// Seek to the position under the cursor while the seek bar is pressed, at most one seek
+    /// at a time, and where it is released.
+    fn scrub(
+        mut bars: Query<(&mut SeekBar, &Interaction, &ComputedNode, &GlobalTransform)>,
+        mut sinks: Query<&mut VideoSink>,
+        windows: Query<&Window, With<PrimaryWindow>>,
+    ) {
+        let cursor = windows
+            .single()
+            .ok()
+            .and_then(Window::physical_cursor_position);
+        for (mut bar, interaction, node, transform) in &mut bars {
+            if *interaction != Interaction::Pressed {
+                let (scrubbed, seeked) = (bar.scrubbed.take(), bar.seeked.take());
+                // Seek to where the seek bar was released, unless the last seek already did
+                if let Some(position) = scrubbed
+                    && scrubbed != seeked
+                    && let Ok(mut sink) = sinks.get_mut(bar.video)
+                {
+                    sink.seek(position);
+                }
+                continue;
+            }
+            let (Some(cursor), Ok(mut sink)) = (cursor, sinks.get_mut(bar.video)) else {
+                continue;
+            };
+            let Some(duration) = sink.duration() else {
+                continue;
+            };
+            if node.size().x <= 0.0 {
+                continue;
+            }
+            // UI node transforms are centered, in physical pixels
+            let left = transform.translation().x - node.size().x / 2.0;
+            let fraction = ((cursor.x - left) / node.size().x).clamp(0.0, 1.0);
+            let position = duration.mul_f32(fraction);
+            bar.scrubbed = Some(position);
+            // Wait for the previous seek to display its frame, rather than restarting
+            // decoding on every frame of the drag
+            if bar.seeked != Some(position) && !sink.is_seeking() {
+                bar.seeked = Some(position);
+                sink.seek(position);
+            }
+        }
+    }
+
+    fn update_controls(
+        sinks: Query<&VideoSink>,
+        mut play_buttons: Query<(&PlayPauseButton, &mut Text), Without<TimeDisplay>>,
+        mut times: Query<(&TimeDisplay, &mut Text), Without<PlayPauseButton>>,
+        mut fills: Query<(&SeekBarFill, &mut Node)>,
+        bars: Query<&SeekBar>,
+    ) {
+        for (button, mut text) in &mut play_buttons {
+            let paused = sinks.get(button.0).is_ok_and(VideoSink::is_paused);
+            let label = if paused { "Play" } else { "Pause" };
+            if text.0 != label {
+                text.0 = label.to_string();
+            }
+        }
+        // Show where the seek bar is being dragged to, rather than the frame still decoding
+        let scrubbed = |video: Entity| {
+            bars.iter()
+                .find(|bar| bar.video == video)
+                .and_then(|bar| bar.scrubbed)
+        };
+        for (fill, mut node) in &mut fills {
+            let Ok(sink) = sinks.get(fill.0) else {
+                continue;
+            };
+            let Some(duration) = sink.duration().filter(|duration| !duration.is_zero()) else {
+                continue;
+            };
+            let position = scrubbed(fill.0).unwrap_or(sink.position());
+            let width =
+                Val::Percent((position.as_secs_f32() / duration.as_secs_f32() * 100.0).min(100.0));
+            if node.width != width {
+                node.width = width;
+            }
+        }
+        for (time, mut text) in &mut times {
+            let Ok(sink) = sinks.get(time.0) else {
+                continue;
+            };
+            let position = scrubbed(time.0).unwrap_or(sink.position());
+            let label = match sink.duration() {
+                Some(duration) => format!("{} / {}", format_time(position), format_time(duration)),
+                None => format_time(position),
+            };
+            if text.0 != label {
+                text.0 = label;
+            }
+        }
+    }
+}
+
+/// Format `time` as `m:ss`, or `h:mm:ss` from one hour.
+fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+/// A named moment in a video, e.g. to play a sound or shake the camera in sync with the video.
+///
+/// Cue points are loaded from a sidecar file, see [`VideoLoaderSettings::cues_path`][crate::VideoLoaderSettings::cues_path].
+/// A [`CuePointReached`] event is triggered when playback crosses each cue point.
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct CuePoint {
+    /// The name of the cue point.
+    pub name: String,
+    /// The time of the cue point from the start of the video.
+    pub time: Duration,
+}
+
+/// Triggered on the entity of a [`VideoSink`][crate::VideoSink] when playback crosses a [`CuePoint`].
+///
+/// Cue points are triggered again each time a looping video crosses them.
+/// Cue points that are skipped over, e.g. before the start of a [`VideoRange`][crate::VideoRange],
+/// are not triggered.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_av1::{CuePointReached, PlaybackMode, VideoLoaderSettings, VideoPlayer};
+/// fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+///     let source = asset_server.load_with_settings(
+///         "av1/explosion.ivf",
+///         |settings: &mut VideoLoaderSettings| {
+///             settings.cues_path = Some("explosion.cues.ron".to_string());
+///         },
+///     );
+///     commands
+///         .spawn(VideoPlayer::new(source, PlaybackMode::Loop))
+///         .observe(|trigger: Trigger<CuePointReached>| {
+///             if trigger.name == "boom" {
+///                 info!("shake the camera");
+///             }
+///         });
+/// }
+/// ```
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct CuePointReached {
+    /// The name of the cue point.
+    pub name: String,
+    /// The time of the cue point from the start of the video.
+    pub time: Duration,
+}
+
+impl From<&CuePoint> for CuePointReached {
+    fn from(cue: &CuePoint) -> Self {
+        Self {
+            name: cue.name.clone(),
+            time: cue.time,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct CuePointFile {
+    name: String,
+    /// Time in seconds.
+    time: f64,
+}
+
+/// The cue points of a sidecar file, sorted by time.
+///
+/// e.g. `[(name: "boom", time: 2.5)]` or `[{"name": "boom", "time": 2.5}]`
+pub(crate) fn cue_points(file: Vec<CuePointFile>) -> std::io::Result<Vec<CuePoint>> {
+    let mut cues = file
+        .into_iter()
+        .map(|cue| {
+            Ok(CuePoint {
+                name: cue.name,
+                time: Duration::try_from_secs_f64(cue.time).map_err(std::io::Error::other)?,
+            })
+        })
+        .collect::<std::io::Result<Vec<_>>>()?;
+    cues.sort_by_key(|cue| cue.time);
+    Ok(cues)
+}
@@ -2,13 +2,100 @@ use std::time::Duration;
 
 use bevy::prelude::*;
 
+use crate::{
+    chapter::Chapter,
+    chroma_key::ChromaKey,
+    cue::CuePoint,
+    frame_info::{FrameInfo, YuvPlanes},
+    throttle::DecodeThrottle,
+    video::VideoRange,
+};
+
 /// A frame of video.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct VideoFrame {
     /// The video frame image.
     pub image: Image,
     /// The presentation timestamp of this frame.
     pub timestamp: Duration,
+    /// The position of this frame in the video, if different from [`Self::timestamp`],
+    /// e.g. when timestamps keep increasing across loops.
+    pub position: Option<Duration>,
+    /// Metadata describing this frame, if provided by the decoder.
+    pub info: Option<FrameInfo>,
+    /// The raw YUV planes of this frame, if requested by [`DecodeSettings::yuv_planes`].
+    pub planes: Option<YuvPlanes>,
+    /// Time spent decoding this frame.
+    pub decode_time: Duration,
+    /// Time spent converting this frame to [`Self::image`].
+    pub conversion_time: Duration,
+}
+
+/// Settings controlling how a [`Decoder`] decodes frames.
+#[derive(Debug, Clone, Default)]
+pub struct DecodeSettings {
+    /// Include the raw YUV planes in each [`VideoFrame`], in addition to the converted image.
+    pub yuv_planes: bool,
+    /// Only include the raw YUV planes, skipping the conversion to [`VideoFrame::image`],
+    /// which is left uninitialized. Required for bit depths greater than 8.
+    pub yuv_planes_only: bool,
+    /// Key out a background color, making it transparent.
+    pub chroma_key: Option<ChromaKey>,
+    /// How film grain signaled in the video is handled.
+    pub film_grain: FilmGrain,
+    /// Downscale decoded frames, e.g. for videos rendered far away or as thumbnails.
+    pub output_scale: OutputScale,
+}
+
+/// The size of frames output by a [`Decoder`], relative to the size of the video.
+///
+/// Frames are only ever downscaled.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum OutputScale {
+    /// Full resolution.
+    #[default]
+    Full,
+    /// Half width and height.
+    Half,
+    /// Quarter width and height.
+    Quarter,
+    /// Fit within a maximum width and height, preserving aspect ratio.
+    Fit(UVec2),
+}
+
+impl OutputScale {
+    /// The output size of a `width` x `height` frame.
+    pub fn size(&self, width: u32, height: u32) -> (u32, u32) {
+        let (width, height) = match *self {
+            Self::Full => (width, height),
+            Self::Half => (width / 2, height / 2),
+            Self::Quarter => (width / 4, height / 4),
+            Self::Fit(max) => {
+                let scale = (max.x as f32 / width as f32)
+                    .min(max.y as f32 / height as f32)
+                    .min(1.0);
+                (
+                    (width as f32 * scale).round() as u32,
+                    (height as f32 * scale).round() as u32,
+                )
+            }
+        };
+        (width.max(1), height.max(1))
+    }
+}
+
+/// How a [`Decoder`] handles film grain signaled in the video.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FilmGrain {
+    /// Apply film grain to decoded frames.
+    #[default]
+    Apply,
+    /// Do not apply film grain.
+    Skip,
+    /// Do not apply film grain, instead export the parameters in
+    /// [`FrameInfo::film_grain`] so grain can be synthesized when rendering,
+    /// e.g. in a shader at display resolution.
+    Export,
 }
 
 /// A type implementing this trait can decode frames of video.
@@ -18,10 +105,49 @@ pub trait Decoder: Send {
     fn width(&self) -> u32;
     /// The height of a video frame.
     fn height(&self) -> u32;
+    /// The intended display width divided by display height.
+    /// Defaults to the aspect ratio of the frame, override this for anamorphic video.
+    fn display_aspect_ratio(&self) -> f32 {
+        self.width() as f32 / self.height() as f32
+    }
     /// The timebase of the decoded video `(numerator, denominator)`.
     /// For example, 30fps video could be `(1, 30)`.
     /// 23.976fps NTSC could be `(125, 2997)`.
     fn timebase(&self) -> (u32, u32);
+    /// The number of frames in the video, if known.
+    fn frame_count(&self) -> Option<u64> {
+        None
+    }
+    /// The duration of the video, if known.
+    fn duration(&self) -> Option<Duration> {
+        None
+    }
+    /// The duration of a single frame.
+    /// Defaults to [`Self::duration`] divided by [`Self::frame_count`] if both are known,
+    /// otherwise one tick of [`Self::timebase`].
+    fn frame_duration(&self) -> Duration {
+        match (self.duration(), self.frame_count()) {
+            (Some(duration), Some(frame_count)) if frame_count > 0 => {
+                duration.div_f64(frame_count as f64)
+            }
+            _ => {
+                let (num, den) = self.timebase();
+                Duration::from_secs_f64(num as f64 / den as f64)
+            }
+        }
+    }
+    /// Use `throttle` to reduce decoding while the video is offscreen, see [`crate::OffscreenPolicy`].
+    /// The default implementation ignores it, [`crate::OffscreenPolicy::Pause`] is still supported
+    /// as [`Self::decode`] waits for frames to be received.
+    fn set_throttle(&mut self, throttle: DecodeThrottle) {
+        let _ = throttle;
+    }
+    /// Only decode `range` of the video, and loop within it, see [`crate::VideoPlayer::range`].
+    /// The default implementation ignores it, frames outside the range are then still dropped,
+    /// but looping restarts at the start of the video.
+    fn set_range(&mut self, range: VideoRange) {
+        let _ = range;
+    }
     /// Asynchronously decode frames of video and send them through channel `tx`.
     /// If `loop_` is `true`, this function does not return unless there is an error.
     fn decode(
@@ -40,6 +166,23 @@ pub trait Decodable: Send + Sync + 'static {
     /// The type of the decoder of the video frames.
     type Decoder: Decoder + Send;
 
-    /// Build and return a [`Self::Decoder`] of the implementing type
-    fn decoder(&self) -> Self::Decoder;
+    /// Build and return a [`Self::Decoder`] of the implementing type,
+    /// configured with `settings`.
+    fn decoder(&self, settings: &DecodeSettings) -> Self::Decoder;
+
+    /// The [`CuePoint`]s of the video, sorted by time.
+    /// [`crate::CuePointReached`] is triggered when playback crosses each of them.
+    fn cue_points(&self) -> &[CuePoint] {
+        &[]
+    }
+
+    /// The [`Chapter`]s of the video, sorted by start time.
+    fn chapters(&self) -> &[Chapter] {
+        &[]
+    }
+
+    /// The duration of the video, if known without starting a decoder.
+    fn duration(&self) -> Option<Duration> {
+        None
+    }
 }
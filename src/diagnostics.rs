@@ -0,0 +1,254 @@
+use std::{collections::BTreeSet, time::Duration};
+
+use bevy::{
+    diagnostic::{
+        Diagnostic, DiagnosticMeasurement, DiagnosticPath, DiagnosticsStore, RegisterDiagnostic,
+    },
+    ecs::entity::EntityHashMap,
+    platform::time::Instant,
+    prelude::*,
+};
+
+use crate::video_sink::VideoSink;
+
+/// Adds video playback diagnostics to an [`App`], aggregated over all playing videos.
+///
+/// Diagnostics can be displayed with [`LogDiagnosticsPlugin`][bevy::diagnostic::LogDiagnosticsPlugin]
+/// or any overlay that reads the [`DiagnosticsStore`].
+#[derive(Default)]
+pub struct VideoDiagnosticsPlugin {
+    /// Also record diagnostics for each video, under `video/<index>/`,
+    /// e.g. `video/0/decoded_fps`, see [`VideoDiagnosticsIndex`].
+    pub per_video: bool,
+}
+
+/// The index of the per-video diagnostics of the [`VideoSink`] on this entity,
+/// inserted when [`VideoDiagnosticsPlugin::per_video`] is set.
+///
+/// Indices of videos that stopped playing are reused, so the number of diagnostics
+/// only grows with the number of videos playing at the same time.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VideoDiagnosticsIndex(pub usize);
+
+impl VideoDiagnosticsPlugin {
+    /// Frames received from decoders per second.
+    pub const DECODED_FPS: DiagnosticPath = DiagnosticPath::const_new("video/decoded_fps");
+    /// Frames displayed per second.
+    pub const PRESENTED_FPS: DiagnosticPath = DiagnosticPath::const_new("video/presented_fps");
+    /// Total frames that were decoded too late to be displayed,
+    /// including by videos that stopped playing.
+    pub const DROPPED_FRAMES: DiagnosticPath = DiagnosticPath::const_new("video/dropped_frames");
+    /// Decoded frames waiting to be displayed.
+    pub const QUEUE_DEPTH: DiagnosticPath = DiagnosticPath::const_new("video/queue_depth");
+    /// Average time to decode a frame, in milliseconds.
+    pub const DECODE_TIME: DiagnosticPath = DiagnosticPath::const_new("video/decode_time");
+    /// Average time to convert a decoded frame to an image, in milliseconds.
+    pub const CONVERSION_TIME: DiagnosticPath = DiagnosticPath::const_new("video/conversion_time");
+    /// Bytes of image data uploaded per second.
+    pub const BYTES_UPLOADED: DiagnosticPath = DiagnosticPath::const_new("video/bytes_uploaded");
+
+    /// Diagnostic names and suffixes.
+    const DIAGNOSTICS: [(&str, &str); 7] = [
+        ("decoded_fps", " fps"),
+        ("presented_fps", " fps"),
+        ("dropped_frames", ""),
+        ("queue_depth", ""),
+        ("decode_time", " ms"),
+        ("conversion_time", " ms"),
+        ("bytes_uploaded", " B/s"),
+    ];
+
+    fn diagnostic_system(
+        mut state: ResMut<VideoDiagnosticsState>,
+        mut store: ResMut<DiagnosticsStore>,
+        mut sinks: Query<(Entity, &mut VideoSink)>,
+        mut removed: RemovedComponents<VideoSink>,
+        time: Res<Time<Real>>,
+        mut commands: Commands,
+    ) {
+        for entity in removed.read() {
+            state.remove_sink(entity, &mut store, &mut commands);
+        }
+        let delta = time.delta_secs_f64();
+        if delta == 0.0 {
+            return;
+        }
+        let mut total = VideoStats::default();
+        for (entity, mut sink) in &mut sinks {
+            let stats = sink.take_stats();
+            let sink_state = state.sinks.entry(entity).or_default();
+            sink_state.dropped = stats.dropped;
+            let index = sink_state.index;
+            if state.per_video {
+                let index =
+                    index.unwrap_or_else(|| state.add_index(entity, &mut store, &mut commands));
+                stats.measure(&mut store, &Self::per_video_paths(index), delta);
+            }
+            total.add(&stats);
+        }
+        total.dropped += state.removed_dropped;
+        total.measure(
+            &mut store,
+            &[
+                Self::DECODED_FPS,
+                Self::PRESENTED_FPS,
+                Self::DROPPED_FRAMES,
+                Self::QUEUE_DEPTH,
+                Self::DECODE_TIME,
+                Self::CONVERSION_TIME,
+                Self::BYTES_UPLOADED,
+            ],
+            delta,
+        );
+    }
+
+    /// The paths of the per-video diagnostics at `index`.
+    fn per_video_paths(index: usize) -> [DiagnosticPath; 7] {
+        Self::DIAGNOSTICS.map(|(name, _)| DiagnosticPath::new(format!("video/{index}/{name}")))
+    }
+}
+
+/// The diagnostics state of the sinks that are playing.
+#[derive(Resource, Default)]
+struct VideoDiagnosticsState {
+    per_video: bool,
+    sinks: EntityHashMap<SinkDiagnostics>,
+    /// Per-video diagnostics indices no longer used by a sink, the lowest is reused first.
+    free_indices: BTreeSet<usize>,
+    /// The number of per-video diagnostics indices added to the store.
+    index_count: usize,
+    /// Dropped frames of sinks that were removed.
+    removed_dropped: u64,
+}
+
+#[derive(Default)]
+struct SinkDiagnostics {
+    index: Option<usize>,
+    /// Total dropped frames, when last measured.
+    dropped: u64,
+}
+
+impl VideoDiagnosticsState {
+    /// Add the dropped frames of the removed sink of `entity` to the total,
+    /// and free its per-video diagnostics index.
+    fn remove_sink(
+        &mut self,
+        entity: Entity,
+        store: &mut DiagnosticsStore,
+        commands: &mut Commands,
+    ) {
+        let Some(sink) = self.sinks.remove(&entity) else {
+            return;
+        };
+        self.removed_dropped += sink.dropped;
+        let Some(index) = sink.index else {
+            return;
+        };
+        // Not displayed until the index is reused
+        for path in VideoDiagnosticsPlugin::per_video_paths(index) {
+            if let Some(diagnostic) = store.get_mut(&path) {
+                diagnostic.is_enabled = false;
+                diagnostic.clear_history();
+            }
+        }
+        self.free_indices.insert(index);
+        commands
+            .entity(entity)
+            .try_remove::<VideoDiagnosticsIndex>();
+    }
+
+    /// Find a per-video diagnostics index for `entity`, reusing a free one if possible.
+    fn add_index(
+        &mut self,
+        entity: Entity,
+        store: &mut DiagnosticsStore,
+        commands: &mut Commands,
+    ) -> usize {
+        let index = match self.free_indices.pop_first() {
+            Some(index) => index,
+            None => {
+                self.index_count += 1;
+                self.index_count - 1
+            }
+        };
+        let paths = VideoDiagnosticsPlugin::per_video_paths(index);
+        for (path, (_, suffix)) in paths.into_iter().zip(VideoDiagnosticsPlugin::DIAGNOSTICS) {
+            match store.get_mut(&path) {
+                Some(diagnostic) => diagnostic.is_enabled = true,
+                None => store.add(Diagnostic::new(path).with_suffix(suffix)),
+            }
+        }
+        self.sinks.entry(entity).or_default().index = Some(index);
+        commands.entity(entity).insert(VideoDiagnosticsIndex(index));
+        index
+    }
+}
+
+impl Plugin for VideoDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(Self::DECODED_FPS).with_suffix(" fps"))
+            .register_diagnostic(Diagnostic::new(Self::PRESENTED_FPS).with_suffix(" fps"))
+            .register_diagnostic(Diagnostic::new(Self::DROPPED_FRAMES))
+            .register_diagnostic(Diagnostic::new(Self::QUEUE_DEPTH))
+            .register_diagnostic(Diagnostic::new(Self::DECODE_TIME).with_suffix(" ms"))
+            .register_diagnostic(Diagnostic::new(Self::CONVERSION_TIME).with_suffix(" ms"))
+            .register_diagnostic(Diagnostic::new(Self::BYTES_UPLOADED).with_suffix(" B/s"))
+            .insert_resource(VideoDiagnosticsState {
+                per_video: self.per_video,
+                ..default()
+            })
+            .add_systems(PostUpdate, Self::diagnostic_system);
+    }
+}
+
+/// Playback statistics of a [`VideoSink`] since they were last taken.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct VideoStats {
+    pub decoded: u32,
+    pub presented: u32,
+    /// Total dropped frames, not reset when taken.
+    pub dropped: u64,
+    pub queue_depth: usize,
+    pub decode_time: Duration,
+    pub conversion_time: Duration,
+    pub bytes_uploaded: usize,
+}
+
+impl VideoStats {
+    fn add(&mut self, other: &Self) {
+        self.decoded += other.decoded;
+        self.presented += other.presented;
+        self.dropped += other.dropped;
+        self.queue_depth += other.queue_depth;
+        self.decode_time += other.decode_time;
+        self.conversion_time += other.conversion_time;
+        self.bytes_uploaded += other.bytes_uploaded;
+    }
+
+    /// Add measurements to the diagnostics at `paths`, in the order of
+    /// [`VideoDiagnosticsPlugin::DIAGNOSTICS`].
+    fn measure(&self, store: &mut DiagnosticsStore, paths: &[DiagnosticPath; 7], delta: f64) {
+        let mut measure = |path: &DiagnosticPath, value: f64| {
+            if let Some(diagnostic) = store.get_mut(path) {
+                diagnostic.add_measurement(DiagnosticMeasurement {
+                    time: Instant::now(),
+                    value,
+                });
+            }
+        };
+        measure(&paths[0], self.decoded as f64 / delta);
+        measure(&paths[1], self.presented as f64 / delta);
+        measure(&paths[2], self.dropped as f64);
+        measure(&paths[3], self.queue_depth as f64);
+        // Only average over frames that were decoded
+        if self.decoded > 0 {
+            let decoded = self.decoded as f64;
+            measure(&paths[4], self.decode_time.as_secs_f64() * 1000.0 / decoded);
+            measure(
+                &paths[5],
+                self.conversion_time.as_secs_f64() * 1000.0 / decoded,
+            );
+        }
+        measure(&paths[6], self.bytes_uploaded as f64 / delta);
+    }
+}
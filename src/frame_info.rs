@@ -0,0 +1,141 @@
+/// Metadata describing a decoded frame of video.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameInfo {
+    /// The presentation timestamp of this frame, in timebase units of the video stream.
+    pub pts: i64,
+    /// The timestamp of this frame on the playback timeline, in timebase units of the video stream.
+    /// Relative to the first frame, and increasing across loops of the video.
+    pub timeline_pts: i64,
+    /// Whether this is a key frame or an inter frame.
+    pub frame_type: FrameType,
+    /// The chroma subsampling of the YUV planes.
+    pub pixel_layout: PixelLayout,
+    /// Bits per sample of the YUV planes.
+    pub bit_depth: u8,
+    /// The range of YUV sample values.
+    pub color_range: ColorRange,
+    /// Matrix coefficients, as defined by ITU-T H.273, e.g. `1` is BT.709.
+    pub matrix_coefficients: u8,
+    /// Color primaries, as defined by ITU-T H.273, e.g. `1` is BT.709.
+    pub color_primaries: u8,
+    /// Transfer characteristics, as defined by ITU-T H.273, e.g. `13` is sRGB.
+    pub transfer_characteristics: u8,
+    /// The intended display width divided by display height.
+    /// This differs from the image aspect ratio for anamorphic video.
+    pub display_aspect_ratio: f32,
+    /// The film grain synthesis parameters of this frame,
+    /// if requested by [`FilmGrain::Export`][crate::FilmGrain::Export].
+    pub film_grain: Option<FilmGrainParams>,
+}
+
+/// The coding type of a frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FrameType {
+    /// A key frame, decoding can start at this frame.
+    #[default]
+    Key,
+    /// A frame predicted from previous frames.
+    Inter,
+    /// An intra frame that is not a key frame.
+    IntraOnly,
+    /// A switch frame, used to switch between streams.
+    Switch,
+}
+
+/// Chroma subsampling layout of YUV planes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PixelLayout {
+    /// Monochrome, only the Y plane is present.
+    I400,
+    /// 4:2:0, chroma planes are half width and half height.
+    #[default]
+    I420,
+    /// 4:2:2, chroma planes are half width and full height.
+    I422,
+    /// 4:4:4, chroma planes are full width and full height.
+    I444,
+}
+
+/// The range of YUV sample values.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ColorRange {
+    /// Studio range, e.g. 16-235 for 8 bit luma.
+    #[default]
+    Limited,
+    /// Full range, e.g. 0-255 for 8 bit.
+    Full,
+}
+
+/// AV1 film grain synthesis parameters of a frame.
+///
+/// The grain synthesis process is defined in section 7.18.3 of the AV1 specification.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FilmGrainParams {
+    /// Seed of the pseudo-random grain generator.
+    pub random_seed: u16,
+    /// Points of the piecewise linear luma scaling function, as `(value, scaling)`.
+    pub y_points: Vec<(u8, u8)>,
+    /// Chroma scaling is derived from the luma scaling function.
+    pub chroma_scaling_from_luma: bool,
+    /// Points of the piecewise linear Cb scaling function, as `(value, scaling)`.
+    pub cb_points: Vec<(u8, u8)>,
+    /// Points of the piecewise linear Cr scaling function, as `(value, scaling)`.
+    pub cr_points: Vec<(u8, u8)>,
+    /// Shift applied to the scaling function output, 8 to 11.
+    pub scaling_shift: u8,
+    /// Lag of the auto-regressive grain filter, 0 to 3.
+    pub ar_coeff_lag: u8,
+    /// Luma auto-regressive filter coefficients.
+    pub ar_coeffs_y: Vec<i8>,
+    /// Cb auto-regressive filter coefficients.
+    pub ar_coeffs_cb: Vec<i8>,
+    /// Cr auto-regressive filter coefficients.
+    pub ar_coeffs_cr: Vec<i8>,
+    /// Shift applied to the auto-regressive filter coefficients, 6 to 9.
+    pub ar_coeff_shift: u8,
+    /// Shift applied to the Gaussian grain values.
+    pub grain_scale_shift: u8,
+    /// Multiplier of the Cb component in the Cb scaling function input.
+    pub cb_mult: u8,
+    /// Multiplier of the average luma in the Cb scaling function input.
+    pub cb_luma_mult: u8,
+    /// Offset of the Cb scaling function input.
+    pub cb_offset: u16,
+    /// Multiplier of the Cr component in the Cr scaling function input.
+    pub cr_mult: u8,
+    /// Multiplier of the average luma in the Cr scaling function input.
+    pub cr_luma_mult: u8,
+    /// Offset of the Cr scaling function input.
+    pub cr_offset: u16,
+    /// Blend grain blocks where they overlap.
+    pub overlap_flag: bool,
+    /// Clip the output to the limited sample range.
+    pub clip_to_restricted_range: bool,
+}
+
+/// Raw YUV planes of a decoded frame.
+///
+/// The planes have the coded size of the frame, which may be larger than the
+/// [`VideoFrame::image`][crate::VideoFrame::image] cropped to the render size.
+/// Samples with a bit depth greater than 8 are stored as native endian `u16`.
+#[derive(Debug, Clone, Default)]
+pub struct YuvPlanes {
+    /// Width of the luma plane, in samples.
+    pub width: u32,
+    /// Height of the luma plane, in samples.
+    pub height: u32,
+    /// The chroma subsampling of the planes.
+    pub layout: PixelLayout,
+    /// Bits per sample.
+    pub bit_depth: u8,
+    /// The luma plane.
+    pub y: Vec<u8>,
+    /// The U chroma plane, empty for [`PixelLayout::I400`].
+    pub u: Vec<u8>,
+    /// The V chroma plane, empty for [`PixelLayout::I400`].
+    pub v: Vec<u8>,
+    /// Bytes per row of the luma plane.
+    pub y_stride: u32,
+    /// Bytes per row of the chroma planes.
+    pub uv_stride: u32,
+}
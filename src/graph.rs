@@ -0,0 +1,568 @@
+use std::{fmt::Display, ops::Range, time::Duration};
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    platform::collections::HashMap,
+    prelude::*,
+};
+use serde::Deserialize;
+
+use crate::{
+    PlaybackMode,
+    scheduler::DecodeScheduler,
+    systems::{DecodeTask, start_decode_task},
+    video::{VideoPlayer, VideoRange, VideoTime},
+    video_sink::{DrainVideoSink, VideoSink},
+    video_source::VideoSource,
+};
+
+/// An interactive video, made of segments with choices of which segment to play next.
+///
+/// Play it with a [`VideoGraphPlayer`]. Load it from a `.videograph.ron` file:
+///
+/// ```ron
+/// (
+///     start: "intro",
+///     segments: {
+///         "intro": (
+///             video: "story.ivf",
+///             end: Some(Frame(240)),
+///             choices: [
+///                 (label: "Open the door", segment: "door"),
+///                 (label: "Run", segment: "run"),
+///             ],
+///             // Offered from 6 to 10 seconds into the segment
+///             choice_window: Some((6.0, 10.0)),
+///             // Played if no choice is made
+///             next: Some("run"),
+///         ),
+///         "door": (video: "story.ivf", start: Some(Frame(240)), end: Some(Frame(480))),
+///         "run": (video: "run.ivf"),
+///     },
+/// )
+/// ```
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct VideoGraph {
+    /// The name of the first segment.
+    pub start: String,
+    /// The segments of the graph, by name.
+    pub segments: HashMap<String, VideoSegment>,
+}
+
+/// A segment of a [`VideoGraph`].
+#[derive(Debug, Clone)]
+pub struct VideoSegment {
+    /// The video of the segment.
+    pub source: Handle<VideoSource>,
+    /// The part of the video played. Playback switches to the next segment at its end.
+    pub range: VideoRange,
+    /// The segments the viewer can choose to play next.
+    pub choices: Vec<VideoChoice>,
+    /// When the choices are offered, from the start of the segment.
+    /// The window closes early if the segment finishes first.
+    /// `None` offers them from the start of the segment until a choice is made,
+    /// holding the last frame of the segment if necessary.
+    pub choice_window: Option<Range<Duration>>,
+    /// The segment to play next if no choice is made, `None` ends playback.
+    pub next: Option<String>,
+}
+
+/// A choice of the next [`VideoSegment`] to play.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VideoChoice {
+    /// Text describing the choice to the viewer.
+    pub label: String,
+    /// The name of the segment played if chosen.
+    pub segment: String,
+}
+
+impl VideoSegment {
+    /// The segments that may be played after this one.
+    fn candidates(&self) -> impl Iterator<Item = &String> {
+        self.choices
+            .iter()
+            .map(|choice| &choice.segment)
+            .chain(&self.next)
+    }
+}
+
+/// A component for playing a [`VideoGraph`].
+///
+/// Insert this component onto an entity to play the graph, starting with its
+/// [`VideoGraph::start`] segment. A [`VideoPlayer`] is inserted to play each segment.
+/// While a segment plays, every segment that may follow it is preloaded,
+/// so the switch to the chosen segment is seamless.
+///
+/// Observe [`ChoiceWindowOpened`] to present the choices, then call [`VideoGraphPlayer::choose`].
+#[derive(Component)]
+pub struct VideoGraphPlayer {
+    /// The graph to play.
+    pub graph: Handle<VideoGraph>,
+    started: bool,
+    current: Option<String>,
+    window: ChoiceWindow,
+    /// The segments of the open choice window.
+    offered: Vec<String>,
+    choice: Option<String>,
+    /// The segment to play next, once decided.
+    next: Option<Option<String>>,
+    /// The segment queued on the [`VideoSink`].
+    queued: Option<String>,
+    /// Decode tasks of the segments that may be played next.
+    preloaded: Vec<(String, DecodeTask)>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum ChoiceWindow {
+    #[default]
+    Pending,
+    Open,
+    Closed,
+}
+
+/// Triggered on the entity of a [`VideoGraphPlayer`] when a segment starts playing.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct SegmentStarted {
+    /// The name of the segment.
+    pub segment: String,
+}
+
+/// Triggered on the entity of a [`VideoGraphPlayer`] when the choices of the current segment
+/// are offered, see [`VideoGraphPlayer::choose`].
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct ChoiceWindowOpened {
+    /// The choices offered.
+    pub choices: Vec<VideoChoice>,
+}
+
+/// Triggered on the entity of a [`VideoGraphPlayer`] when the choice window closes.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct ChoiceWindowClosed {
+    /// The segment to play next, `None` if playback ends after the current segment.
+    pub segment: Option<String>,
+}
+
+impl VideoGraphPlayer {
+    /// Creates a new [`VideoGraphPlayer`] playing `graph`.
+    pub fn new(graph: Handle<VideoGraph>) -> Self {
+        Self {
+            graph,
+            started: false,
+            current: None,
+            window: ChoiceWindow::Pending,
+            offered: Vec::new(),
+            choice: None,
+            next: None,
+            queued: None,
+            preloaded: Vec::new(),
+        }
+    }
+
+    /// The name of the segment currently playing.
+    pub fn current_segment(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    /// Whether the choices of the current segment are offered.
+    pub fn is_choice_open(&self) -> bool {
+        self.window == ChoiceWindow::Open
+    }
+
+    /// Choose the segment to play after the current one, by its index in [`VideoSegment::choices`].
+    /// Returns `false` if the choice window is not open.
+    pub fn choose(&mut self, choice: usize) -> bool {
+        let Some(segment) = self.offered.get(choice).filter(|_| self.is_choice_open()) else {
+            return false;
+        };
+        self.choice = Some(segment.clone());
+        true
+    }
+
+    fn start_segment(&mut self, segment: String) {
+        self.current = Some(segment);
+        self.window = ChoiceWindow::Pending;
+        self.offered.clear();
+        self.choice = None;
+        self.next = None;
+        self.preloaded.clear();
+    }
+
+    pub(crate) fn start_graphs(
+        mut query: Query<(Entity, &mut VideoGraphPlayer), Without<VideoPlayer>>,
+        graphs: Res<Assets<VideoGraph>>,
+        mut commands: Commands,
+    ) {
+        for (entity, mut graph_player) in &mut query {
+            if graph_player.started {
+                continue;
+            }
+            let Some(graph) = graphs.get(&graph_player.graph) else {
+                continue;
+            };
+            let Some(segment) = graph.segments.get(&graph.start) else {
+                warn!("Video graph start segment {:?} not found", graph.start);
+                graph_player.started = true;
+                continue;
+            };
+            graph_player.started = true;
+            graph_player.start_segment(graph.start.clone());
+            commands.entity(entity).insert(
+                VideoPlayer::new(segment.source.clone(), PlaybackMode::Remove)
+                    .with_range(segment.range),
+            );
+            commands.trigger_targets(
+                SegmentStarted {
+                    segment: graph.start.clone(),
+                },
+                entity,
+            );
+        }
+    }
+
+    /// Preload the segments that may follow the current one, manage its choice window,
+    /// and queue the next segment once decided.
+    pub(crate) fn update_graphs(
+        mut query: Query<(
+            Entity,
+            &mut VideoGraphPlayer,
+            &mut VideoPlayer,
+            &mut VideoSink,
+            Has<DrainVideoSink>,
+        )>,
+        graphs: Res<Assets<VideoGraph>>,
+        video_sources: Res<Assets<VideoSource>>,
+        scheduler: Res<DecodeScheduler>,
+        mut commands: Commands,
+    ) {
+        for (entity, mut graph_player, mut player, mut sink, drain) in &mut query {
+            let Some(graph) = graphs.get(&graph_player.graph) else {
+                continue;
+            };
+            // The queued segment started playing
+            if graph_player.queued.is_some() && !sink.has_queued() {
+                let name = graph_player.queued.take().unwrap_or_default();
+                if let Some(segment) = graph.segments.get(&name) {
+                    player.source = segment.source.clone();
+                    player.range = segment.range;
+                }
+                graph_player.start_segment(name.clone());
+                commands.trigger_targets(SegmentStarted { segment: name }, entity);
+            }
+            if graph_player.queued.is_some() {
+                continue;
+            }
+            let Some(segment) = graph_player
+                .current
+                .as_ref()
+                .and_then(|current| graph.segments.get(current))
+            else {
+                continue;
+            };
+
+            if graph_player.next.is_none() {
+                for name in segment.candidates() {
+                    if graph_player
+                        .preloaded
+                        .iter()
+                        .any(|(other, _)| other == name)
+                    {
+                        continue;
+                    }
+                    let Some(candidate) = graph.segments.get(name) else {
+                        continue;
+                    };
+                    let Some(video_source) = video_sources.get(&candidate.source) else {
+                        continue;
+                    };
+                    let decode_task = start_decode_task(
+                        video_source,
+                        &player.settings,
+                        false,
+                        candidate.range,
+                        sink.throttle().clone(),
+                        sink.priority().clone(),
+                        &scheduler,
+                    );
+                    graph_player.preloaded.push((name.clone(), decode_task));
+                }
+            }
+
+            if segment.choices.is_empty() {
+                graph_player.next = Some(segment.next.clone());
+            } else {
+                let position = sink.range_position();
+                let window = segment
+                    .choice_window
+                    .clone()
+                    .unwrap_or(Duration::ZERO..Duration::MAX);
+                match graph_player.window {
+                    ChoiceWindow::Pending if position >= window.start => {
+                        graph_player.window = ChoiceWindow::Open;
+                        graph_player.offered = segment
+                            .choices
+                            .iter()
+                            .map(|choice| choice.segment.clone())
+                            .collect();
+                        commands.trigger_targets(
+                            ChoiceWindowOpened {
+                                choices: segment.choices.clone(),
+                            },
+                            entity,
+                        );
+                    }
+                    // The segment finished before the window started
+                    ChoiceWindow::Pending if drain || sink.is_ended() => {
+                        graph_player.window = ChoiceWindow::Closed;
+                        graph_player.next = Some(segment.next.clone());
+                        commands.trigger_targets(
+                            ChoiceWindowClosed {
+                                segment: segment.next.clone(),
+                            },
+                            entity,
+                        );
+                    }
+                    ChoiceWindow::Open => {
+                        let next = match graph_player.choice.take() {
+                            Some(choice) => Some(Some(choice)),
+                            // The window also closes if the segment finishes before it ends
+                            None if position >= window.end
+                                || (segment.choice_window.is_some()
+                                    && (drain || sink.is_ended())) =>
+                            {
+                                Some(segment.next.clone())
+                            }
+                            None => None,
+                        };
+                        if let Some(next) = next {
+                            graph_player.window = ChoiceWindow::Closed;
+                            graph_player.next = Some(next.clone());
+                            commands.trigger_targets(ChoiceWindowClosed { segment: next }, entity);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+
+            match graph_player.next.clone() {
+                // Wait for a choice
+                None => sink.set_hold(true),
+                Some(None) => {
+                    graph_player.preloaded.clear();
+                    sink.set_hold(false);
+                }
+                Some(Some(next)) => {
+                    let preloaded = graph_player
+                        .preloaded
+                        .iter()
+                        .position(|(name, _)| *name == next);
+                    match preloaded {
+                        Some(index) => {
+                            let (_, decode_task) = graph_player.preloaded.swap_remove(index);
+                            sink.queue(decode_task);
+                            sink.set_hold(false);
+                            graph_player.preloaded.clear();
+                            graph_player.queued = Some(next);
+                        }
+                        // Wait for the segment to load
+                        None if graph.segments.contains_key(&next) => sink.set_hold(true),
+                        None => {
+                            warn!("Video graph segment {next:?} not found");
+                            graph_player.next = Some(None);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Loads `.videograph.ron` files as [`VideoGraph`] [`Assets`].
+#[derive(Default)]
+pub struct VideoGraphLoader;
+
+#[derive(Debug)]
+pub enum VideoGraphLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+    /// A time in seconds is negative, not finite or too large.
+    Time(std::time::TryFromFloatSecsError),
+    /// The choice window of the segment ends before it starts.
+    ChoiceWindow(String),
+}
+
+impl Display for VideoGraphLoaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl std::error::Error for VideoGraphLoaderError {}
+
+#[derive(Deserialize)]
+struct VideoGraphFile {
+    start: String,
+    segments: std::collections::HashMap<String, VideoSegmentFile>,
+}
+
+#[derive(Deserialize)]
+struct VideoSegmentFile {
+    /// Path of the video, relative to the graph file.
+    video: String,
+    #[serde(default)]
+    start: Option<VideoTimeFile>,
+    #[serde(default)]
+    end: Option<VideoTimeFile>,
+    #[serde(default)]
+    choices: Vec<VideoChoice>,
+    /// Start and end in seconds.
+    #[serde(default)]
+    choice_window: Option<(f64, f64)>,
+    #[serde(default)]
+    next: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+enum VideoTimeFile {
+    Seconds(f64),
+    Frame(u64),
+}
+
+impl TryFrom<VideoTimeFile> for VideoTime {
+    type Error = VideoGraphLoaderError;
+
+    fn try_from(time: VideoTimeFile) -> Result<Self, Self::Error> {
+        Ok(match time {
+            VideoTimeFile::Seconds(seconds) => Self::Time(seconds_to_duration(seconds)?),
+            VideoTimeFile::Frame(frame) => Self::Frame(frame),
+        })
+    }
+}
+
+fn seconds_to_duration(seconds: f64) -> Result<Duration, VideoGraphLoaderError> {
+    Duration::try_from_secs_f64(seconds).map_err(VideoGraphLoaderError::Time)
+}
+
+impl AssetLoader for VideoGraphLoader {
+    type Asset = VideoGraph;
+    type Settings = ();
+    type Error = VideoGraphLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<VideoGraph, Self::Error> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(VideoGraphLoaderError::Io)?;
+        let file: VideoGraphFile =
+            ron::de::from_bytes(&bytes).map_err(VideoGraphLoaderError::Ron)?;
+        let mut segments = HashMap::default();
+        for (name, segment) in file.segments {
+            let path = load_context
+                .asset_path()
+                .resolve_embed(&segment.video)
+                .map_err(|e| VideoGraphLoaderError::Io(std::io::Error::other(e)))?;
+            let choice_window = match segment.choice_window {
+                Some((start, end)) => {
+                    let (start, end) = (seconds_to_duration(start)?, seconds_to_duration(end)?);
+                    if start > end {
+                        return Err(VideoGraphLoaderError::ChoiceWindow(name));
+                    }
+                    Some(start..end)
+                }
+                None => None,
+            };
+            segments.insert(
+                name,
+                VideoSegment {
+                    source: load_context.load(path),
+                    range: VideoRange {
+                        start: segment.start.map(VideoTime::try_from).transpose()?,
+                        end: segment.end.map(VideoTime::try_from).transpose()?,
+                        loop_start: None,
+                    },
+                    choices: segment.choices,
+                    choice_window,
+                    next: segment.next,
+                },
+            );
+        }
+        Ok(VideoGraph {
+            start: file.start,
+            segments,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["videograph.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::tests::pending_decode_task;
+
+    #[derive(Resource, Default)]
+    struct Closed(Vec<Option<String>>);
+
+    #[test]
+    fn segment_ends_before_choice_window() {
+        let mut app = App::new();
+        app.init_resource::<Assets<VideoGraph>>()
+            .init_resource::<Assets<VideoSource>>()
+            .init_resource::<DecodeScheduler>()
+            .init_resource::<Closed>()
+            .add_systems(Update, VideoGraphPlayer::update_graphs)
+            .add_observer(
+                |trigger: Trigger<ChoiceWindowClosed>, mut closed: ResMut<Closed>| {
+                    closed.0.push(trigger.event().segment.clone());
+                },
+            );
+        let segment = VideoSegment {
+            source: Handle::default(),
+            range: VideoRange::default(),
+            choices: vec![VideoChoice {
+                label: "Run".to_string(),
+                segment: "run".to_string(),
+            }],
+            // Starts after the end of the video
+            choice_window: Some(Duration::from_secs(10)..Duration::from_secs(12)),
+            next: None,
+        };
+        let graph = app
+            .world_mut()
+            .resource_mut::<Assets<VideoGraph>>()
+            .add(VideoGraph {
+                start: "intro".to_string(),
+                segments: HashMap::from_iter([("intro".to_string(), segment)]),
+            });
+        let mut graph_player = VideoGraphPlayer::new(graph);
+        graph_player.started = true;
+        graph_player.start_segment("intro".to_string());
+        let (_tx, rx) = async_channel::bounded(1);
+        let mut sink = VideoSink::new(Handle::default(), pending_decode_task(rx));
+        sink.set_hold(true);
+        // The decoder finished
+        let entity = app
+            .world_mut()
+            .spawn((
+                graph_player,
+                VideoPlayer::new(Handle::default(), PlaybackMode::Remove),
+                sink,
+                DrainVideoSink,
+            ))
+            .id();
+
+        app.update();
+
+        assert_eq!(app.world().resource::<Closed>().0, vec![None]);
+        let entity = app.world().entity(entity);
+        assert!(!entity.get::<VideoGraphPlayer>().unwrap().is_choice_open());
+        assert!(!entity.get::<VideoSink>().unwrap().is_held());
+    }
+}
@@ -31,25 +31,176 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
             },
         );
 }
+```
+
+# Poster frames
+
+A `poster` [`Image`] can be decoded from a frame of a [`VideoSource`] at load time,
+and loaded as a labeled sub-asset, e.g. `video.ivf#poster`, by setting
+[`VideoLoaderSettings::poster`].
+Additional frames can be decoded using [`VideoLoaderSettings::frames`].
+
+```rust
+# use bevy::prelude::*;
+# use bevy_av1::VideoLoaderSettings;
+# use std::time::Duration;
+fn setup(asset_server: Res<AssetServer>) {
+    let settings = |settings: &mut VideoLoaderSettings| {
+        settings.poster = Some(Duration::ZERO);
+        settings.frames = vec![Duration::from_secs_f32(2.5)];
+    };
+    let poster: Handle<Image> =
+        asset_server.load_with_settings("av1/cosmos-laundromat.ivf#poster", settings);
+    let thumbnail: Handle<Image> =
+        asset_server.load_with_settings("av1/cosmos-laundromat.ivf#frame@2.5s", settings);
+}
+```
+
+Short clips can also be decoded entirely at load time into an `atlas` [`Image`]
+(with an `atlas_layout` [`TextureAtlasLayout`]) or an `array` texture [`Image`],
+see [`VideoLoaderSettings::atlas`] and [`VideoLoaderSettings::texture_array`].
+
+# Clips
+
+Set a [`VideoRange`] on a [`VideoPlayer`] to play only part of a video, so one video can
+contain several clips. With [`PlaybackMode::Loop`] the range can loop from a different start.
+
+# Cue points
+
+Load [`CuePoint`]s from a RON or JSON sidecar file with [`VideoLoaderSettings::cues_path`]
+to trigger a [`CuePointReached`] event on the [`VideoSink`] entity when playback crosses each one,
+including on every loop.
+
+# Playlists
+
+Insert a [`VideoPlaylist`] to play several [`VideoSource`]s one after the other into the
+same [`VideoSink`] image, in order or shuffled.
+
+# Interactive video
+
+Load a [`VideoGraph`] of segments with choices between them, e.g. from a `.videograph.ron` file,
+and play it with a [`VideoGraphPlayer`]. Segments that may be chosen are preloaded,
+so switching to the chosen segment is seamless.
+
+# Chapters
+
+Load [`Chapter`]s from a sidecar file with [`VideoLoaderSettings::chapters_path`], then skip
+between them with [`VideoSink::next_chapter`] and [`VideoSink::previous_chapter`],
+or jump anywhere with [`VideoSink::seek`].
+
+# Subtitles
+
+Load [`Subtitles`] from WebVTT or SubRip files and add [`VideoSubtitles`] next to the
+[`VideoPlayer`] to follow the active caption. With the `bevy_ui` feature,
+`SubtitleText` displays it in a UI `Text`.
+
+# Playback controls
+
+[`VideoSink::pause`], [`VideoSink::seek`] and [`VideoSink::position`] control playback.
+With the `bevy_ui` feature, add `VideoControlsPlugin` and spawn a `VideoControls` node
+for a standard play/pause button, seek bar, time display and fullscreen toggle.
+
+# Transitions
+
+Insert a [`VideoTransition`] to crossfade, wipe or dip to black from one [`VideoSink`]
+to another, into a single output [`Image`].
+
+# Synchronized videos
+
+Add [`SyncToClock`] to several [`VideoPlayer`]s to start, pause, seek and loop them in lockstep
+with a shared [`VideoClock`].
+
+# Offscreen videos
+
+Insert an [`OffscreenPolicy`] next to the [`VideoPlayer`] to pause or reduce decoding
+while the entity is not visible, according to its [`ViewVisibility`].
+
+# Decode scheduling
+
+Insert a [`DecodeScheduler`] resource to limit how many videos decode at the same time,
+or to decode on a dedicated thread pool. Add a [`DecodePriority`] to a [`VideoPlayer`]
+to decode it before other videos.
+
+# Diagnostics
+
+Add [`VideoDiagnosticsPlugin`] to record decoded and presented frame rates, dropped frames,
+queue depth, decode and conversion times and uploaded bytes in the
+[`DiagnosticsStore`][bevy::diagnostic::DiagnosticsStore].
+
+# Headless decoding
+
+[`VideoSource::frames`] decodes frames synchronously without an [`App`],
+e.g. in tools or tests.
+
+```rust,no_run
+# use bevy_av1::VideoSource;
+# fn main() -> bevy::prelude::Result {
+let source = VideoSource::new(std::fs::read("assets/av1/cosmos-laundromat.ivf")?);
+for frame in source.frames()? {
+    let frame = frame?;
+    println!("{:?} {}x{}", frame.timestamp, frame.image.width(), frame.image.height());
+}
+# Ok(())
+# }
 ```
  */
 
-use bevy::prelude::*;
+use bevy::{image::TextureAtlasPlugin, prelude::*};
 
 mod av1;
+mod chapter;
+mod chroma_key;
+mod clock;
+#[cfg(feature = "bevy_ui")]
+mod controls;
+mod cue;
 mod decodable;
+mod diagnostics;
+mod frame_info;
+mod graph;
+mod playlist;
+mod scheduler;
+mod subtitles;
 mod systems;
+mod throttle;
+mod transition;
 mod video;
 mod video_sink;
 mod video_source;
 pub use crate::{
-    decodable::{Decodable, Decoder, VideoFrame},
-    video::{PlaybackMode, VideoPlayer},
-    video_sink::{VideoSink, VideoTargetAssets},
-    video_source::{AddVideoSource, VideoSource},
+    chapter::Chapter,
+    chroma_key::ChromaKey,
+    clock::{SyncToClock, VideoClock},
+    cue::{CuePoint, CuePointReached},
+    decodable::{Decodable, DecodeSettings, Decoder, FilmGrain, OutputScale, VideoFrame},
+    diagnostics::{VideoDiagnosticsIndex, VideoDiagnosticsPlugin},
+    frame_info::{ColorRange, FilmGrainParams, FrameInfo, FrameType, PixelLayout, YuvPlanes},
+    graph::{
+        ChoiceWindowClosed, ChoiceWindowOpened, SegmentStarted, VideoChoice, VideoGraph,
+        VideoGraphLoaderError, VideoGraphPlayer, VideoSegment,
+    },
+    playlist::{PlaylistMode, VideoPlaylist},
+    scheduler::{DecodePriority, DecodeScheduler},
+    subtitles::{SubtitleChanged, SubtitleCue, Subtitles, SubtitlesLoaderError, VideoSubtitles},
+    throttle::{DecodeThrottle, OffscreenPolicy},
+    transition::{TransitionKind, VideoTransition, WipeDirection},
+    video::{PlaybackMode, VideoPlayer, VideoRange, VideoTime},
+    video_sink::{VideoResized, VideoSink, VideoTargetAssets},
+    video_source::{AddVideoSource, AlphaLayout, VideoFrames, VideoLoaderSettings, VideoSource},
+};
+#[cfg(feature = "bevy_ui")]
+pub use crate::{
+    controls::{VideoControls, VideoControlsPlugin},
+    subtitles::SubtitleText,
 };
 use crate::{
-    systems::{play_videos, poll_video_sinks, render_video_sinks},
+    graph::VideoGraphLoader,
+    subtitles::SubtitlesLoader,
+    systems::{
+        play_videos, poll_video_sinks, reload_video_sinks, render_video_sinks, seek_video_sinks,
+        throttle_offscreen_videos, update_decode_priorities,
+    },
+    transition::VideoTransitionPlugin,
     video_sink::VideoFrameUpdated,
     video_source::VideoLoader,
 };
@@ -63,10 +214,46 @@ pub struct VideoPlugin;
 
 impl Plugin for VideoPlugin {
     fn build(&self, app: &mut App) {
+        // Required for the `atlas_layout` labeled sub-asset of VideoSource
+        if !app.is_plugin_added::<TextureAtlasPlugin>() {
+            app.add_plugins(TextureAtlasPlugin);
+        }
+        app.add_plugins(VideoTransitionPlugin);
         app.add_video_source::<VideoSource>()
             .init_asset_loader::<VideoLoader>()
+            .init_asset::<VideoGraph>()
+            .init_asset_loader::<VideoGraphLoader>()
+            .init_asset::<Subtitles>()
+            .init_asset_loader::<SubtitlesLoader>()
             .add_event::<VideoFrameUpdated>()
-            .add_systems(Update, poll_video_sinks);
+            .init_resource::<DecodeScheduler>()
+            .add_systems(
+                Update,
+                (
+                    poll_video_sinks,
+                    throttle_offscreen_videos,
+                    update_decode_priorities,
+                    VideoPlaylist::start_playlists,
+                    VideoPlaylist::queue_playlist_items,
+                    VideoGraphPlayer::start_graphs,
+                    VideoGraphPlayer::update_graphs,
+                    VideoClock::update_clocks,
+                ),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    VideoTransition::update_transitions,
+                    VideoSubtitles::update_subtitles,
+                ),
+            );
+        #[cfg(feature = "bevy_ui")]
+        app.add_systems(
+            PostUpdate,
+            SubtitleText::update_text
+                .after(VideoSubtitles::update_subtitles)
+                .before(bevy::ui::UiSystem::Prepare),
+        );
     }
 }
 
@@ -75,8 +262,17 @@ impl AddVideoSource for App {
     where
         T: Decodable + Asset,
     {
-        self.init_asset::<T>()
-            .add_systems(Update, (play_videos::<T>, render_video_sinks::<T>));
+        self.init_asset::<T>().add_systems(
+            Update,
+            (
+                play_videos::<T>,
+                reload_video_sinks::<T>.before(seek_video_sinks::<T>),
+                seek_video_sinks::<T>
+                    .after(VideoClock::update_clocks)
+                    .before(render_video_sinks::<T>),
+                render_video_sinks::<T>,
+            ),
+        );
         self
     }
 }
@@ -94,7 +290,9 @@ impl VideoTargetApp for App {
         self.init_resource::<VideoTargetAssets<A>>().add_systems(
             PostUpdate,
             (
-                VideoTargetAssets::<A>::update_target_assets,
+                // Also update the targets of transition output images
+                VideoTargetAssets::<A>::update_target_assets
+                    .after(VideoTransition::update_transitions),
                 VideoTargetAssets::<A>::remove_unused_image_target_assets,
                 VideoTargetAssets::<A>::remove_unused_target_assets,
             ),
@@ -0,0 +1,202 @@
+use std::{
+    collections::VecDeque,
+    hash::{BuildHasher, RandomState},
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    PlaybackMode,
+    scheduler::DecodeScheduler,
+    systems::start_decode_task,
+    video::{VideoPlayer, VideoRange},
+    video_sink::VideoSink,
+    video_source::VideoSource,
+};
+
+/// A component for playing a list of videos one after the other.
+///
+/// Insert this component onto an entity to play its items into a single [`VideoSink`] image.
+/// A [`VideoPlayer`] for the first item is inserted if the entity has none,
+/// otherwise the [`VideoPlayer::settings`] and [`VideoPlayer::mode`] of the existing player
+/// are used for every item. The [`PlaybackMode`] applies when the playlist finishes,
+/// [`PlaybackMode::Loop`] repeats the current item forever.
+/// The [`VideoPlayer::range`] only applies to the first item.
+///
+/// The next item starts decoding while the current item is playing,
+/// so there is no blank frame between items.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_av1::{PlaylistMode, VideoPlaylist};
+/// fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+///     commands.spawn(VideoPlaylist::new(
+///         vec![
+///             asset_server.load("av1/channel1.ivf"),
+///             asset_server.load("av1/channel2.ivf"),
+///         ],
+///         PlaylistMode::Shuffle,
+///     ));
+/// }
+/// ```
+#[derive(Component, Debug, Clone)]
+pub struct VideoPlaylist {
+    /// The videos to play.
+    pub items: Vec<Handle<VideoSource>>,
+    /// The order in which the items are played.
+    pub mode: PlaylistMode,
+    /// Index of the item currently playing.
+    current: Option<usize>,
+    /// Indices of the items to play next, in order.
+    upcoming: VecDeque<usize>,
+    /// The next item has been queued on the [`VideoSink`].
+    queued: bool,
+    /// State of the random number generator used to shuffle.
+    rng: u64,
+}
+
+/// The order in which a [`VideoPlaylist`] plays its items.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PlaylistMode {
+    /// Play each item once, in order.
+    #[default]
+    Sequential,
+    /// Play the items in order, then repeat from the first item forever.
+    RepeatAll,
+    /// Play the items in a random order, reshuffled after every item has played once, forever.
+    Shuffle,
+}
+
+impl VideoPlaylist {
+    /// Creates a new [`VideoPlaylist`] playing `items` in the order of `mode`.
+    pub fn new(items: Vec<Handle<VideoSource>>, mode: PlaylistMode) -> Self {
+        Self {
+            items,
+            mode,
+            current: None,
+            upcoming: VecDeque::new(),
+            queued: false,
+            // Any non-zero seed
+            rng: RandomState::new().hash_one(0u64) | 1,
+        }
+    }
+
+    /// The index in [`Self::items`] of the item currently playing.
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    /// The item currently playing.
+    pub fn current_item(&self) -> Option<&Handle<VideoSource>> {
+        self.items.get(self.current?)
+    }
+
+    /// The index of the item to play after the current one, if any.
+    fn peek_next(&mut self) -> Option<usize> {
+        if self.upcoming.is_empty() {
+            self.refill();
+        }
+        self.upcoming
+            .front()
+            .copied()
+            .filter(|&index| index < self.items.len())
+    }
+
+    /// Make the next item current.
+    fn advance(&mut self) {
+        self.peek_next();
+        self.current = self.upcoming.pop_front();
+        self.queued = false;
+    }
+
+    fn refill(&mut self) {
+        let len = self.items.len();
+        match self.mode {
+            // Only the first pass
+            PlaylistMode::Sequential if self.current.is_none() => self.upcoming.extend(0..len),
+            PlaylistMode::Sequential => (),
+            PlaylistMode::RepeatAll => self.upcoming.extend(0..len),
+            PlaylistMode::Shuffle => {
+                let mut order: Vec<usize> = (0..len).collect();
+                // Fisher-Yates shuffle
+                for i in (1..len).rev() {
+                    order.swap(i, (self.next_random() % (i as u64 + 1)) as usize);
+                }
+                // Don't play the same item twice in a row across passes
+                if len > 1 && order.first() == self.current.as_ref() {
+                    order.swap(0, len - 1);
+                }
+                self.upcoming.extend(order);
+            }
+        }
+    }
+
+    /// xorshift64
+    fn next_random(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+
+    pub(crate) fn start_playlists(
+        mut query: Query<
+            (Entity, &mut VideoPlaylist, Option<&mut VideoPlayer>),
+            Added<VideoPlaylist>,
+        >,
+        mut commands: Commands,
+    ) {
+        for (entity, mut playlist, player) in &mut query {
+            playlist.advance();
+            let Some(source) = playlist.current_item().cloned() else {
+                continue;
+            };
+            match player {
+                Some(mut player) => player.source = source,
+                None => {
+                    commands
+                        .entity(entity)
+                        .insert(VideoPlayer::new(source, PlaybackMode::Remove));
+                }
+            }
+        }
+    }
+
+    /// Start decoding the next item of each playlist, and advance playlists
+    /// once their queued item is playing.
+    pub(crate) fn queue_playlist_items(
+        mut query: Query<(&mut VideoPlaylist, &mut VideoPlayer, &mut VideoSink)>,
+        video_sources: Res<Assets<VideoSource>>,
+        scheduler: Res<DecodeScheduler>,
+    ) {
+        for (mut playlist, mut player, mut sink) in &mut query {
+            if playlist.queued && !sink.has_queued() {
+                playlist.advance();
+                if let Some(source) = playlist.current_item() {
+                    player.source = source.clone();
+                }
+            }
+            // The current item never finishes
+            if playlist.queued || matches!(player.mode, PlaybackMode::Loop) {
+                continue;
+            }
+            let Some(next) = playlist.peek_next() else {
+                continue;
+            };
+            let Some(video_source) = video_sources.get(&playlist.items[next]) else {
+                continue;
+            };
+            let decode_task = start_decode_task(
+                video_source,
+                &player.settings,
+                false,
+                VideoRange::default(),
+                sink.throttle().clone(),
+                sink.priority().clone(),
+                &scheduler,
+            );
+            sink.queue(decode_task);
+            playlist.queued = true;
+        }
+    }
+}
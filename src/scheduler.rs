@@ -0,0 +1,376 @@
+use std::{
+    cmp::Reverse,
+    pin::Pin,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicI32, Ordering},
+    },
+    task::{Context, Poll, Waker},
+};
+
+use bevy::{
+    prelude::*,
+    tasks::{ComputeTaskPool, Task, TaskPool, TaskPoolBuilder},
+};
+
+/// The priority of a video when the [`DecodeScheduler`] limits concurrent decoding.
+///
+/// Insert this component next to a [`VideoPlayer`][crate::VideoPlayer].
+/// Videos with a higher priority decode first, the default priority is `0`.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DecodePriority(pub i32);
+
+/// Schedules the decode tasks of all videos.
+///
+/// By default videos decode on the [`ComputeTaskPool`] without limit.
+/// Insert this resource before starting playback to limit the number of videos
+/// decoding at the same time, or to decode on a dedicated thread pool.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_av1::{DecodeScheduler, VideoPlugin};
+/// App::new()
+///     .insert_resource(
+///         DecodeScheduler::default()
+///             .with_max_concurrent_decodes(4)
+///             .with_dedicated_threads(2),
+///     )
+///     .add_plugins((MinimalPlugins, AssetPlugin::default(), VideoPlugin));
+/// ```
+#[derive(Resource, Clone, Default)]
+pub struct DecodeScheduler {
+    state: Arc<Mutex<SchedulerState>>,
+    thread_pool: Option<Arc<TaskPool>>,
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    max_concurrent_decodes: Option<usize>,
+    running: usize,
+    next_id: u64,
+    /// Tasks waiting to decode, with their id, priority and waker.
+    waiting: Vec<(u64, i32, Waker)>,
+}
+
+impl DecodeScheduler {
+    /// Limit the number of videos decoding at the same time.
+    pub fn with_max_concurrent_decodes(self, max_concurrent_decodes: usize) -> Self {
+        self.set_max_concurrent_decodes(Some(max_concurrent_decodes));
+        self
+    }
+
+    /// Decode on a dedicated thread pool with `num_threads` threads,
+    /// instead of the [`ComputeTaskPool`].
+    pub fn with_dedicated_threads(mut self, num_threads: usize) -> Self {
+        self.thread_pool = Some(Arc::new(
+            TaskPoolBuilder::new()
+                .num_threads(num_threads)
+                .thread_name("Video Decode Task Pool".to_string())
+                .build(),
+        ));
+        self
+    }
+
+    /// The maximum number of videos decoding at the same time, `None` is unlimited.
+    pub fn max_concurrent_decodes(&self) -> Option<usize> {
+        self.state.lock().unwrap().max_concurrent_decodes
+    }
+
+    /// Set the maximum number of videos decoding at the same time, `None` is unlimited.
+    /// This applies to videos that are already playing.
+    pub fn set_max_concurrent_decodes(&self, max_concurrent_decodes: Option<usize>) {
+        let mut state = self.state.lock().unwrap();
+        // A limit of zero would never decode
+        state.max_concurrent_decodes = max_concurrent_decodes.map(|max| max.max(1));
+        state.wake_waiting();
+    }
+
+    /// Spawn a decode task, scheduled with `priority`.
+    pub(crate) fn spawn<T: Send + 'static>(
+        &self,
+        future: impl Future<Output = T> + Send + 'static,
+        priority: Arc<AtomicI32>,
+    ) -> Task<T> {
+        let scheduled = self.schedule(future, priority);
+        match &self.thread_pool {
+            Some(thread_pool) => thread_pool.spawn(scheduled),
+            None => ComputeTaskPool::get().spawn(scheduled),
+        }
+    }
+
+    /// Wrap `future` so that it only runs when scheduled with `priority`.
+    fn schedule<T>(
+        &self,
+        future: impl Future<Output = T> + Send + 'static,
+        priority: Arc<AtomicI32>,
+    ) -> Scheduled<T> {
+        let id = {
+            let mut state = self.state.lock().unwrap();
+            state.next_id += 1;
+            state.next_id
+        };
+        Scheduled {
+            future: Box::pin(future),
+            state: self.state.clone(),
+            id,
+            priority,
+        }
+    }
+}
+
+impl SchedulerState {
+    /// Start decoding task `id`, or wait if no decode is available to a task of `priority`.
+    fn try_start(&mut self, id: u64, priority: i32, waker: &Waker) -> bool {
+        let available = self
+            .max_concurrent_decodes
+            .is_none_or(|max| self.running < max);
+        let outranked = self
+            .waiting
+            .iter()
+            .any(|&(other, other_priority, _)| other != id && other_priority > priority);
+        if available && !outranked {
+            self.running += 1;
+            self.remove_waiting(id);
+            return true;
+        }
+        match self.waiting.iter_mut().find(|(other, ..)| *other == id) {
+            Some(waiting) => *waiting = (id, priority, waker.clone()),
+            None => self.waiting.push((id, priority, waker.clone())),
+        }
+        if available {
+            // Outranked, the higher priority tasks must take the available decodes
+            self.wake_waiting();
+        }
+        false
+    }
+
+    fn remove_waiting(&mut self, id: u64) {
+        self.waiting.retain(|&(other, ..)| other != id);
+    }
+
+    /// Wake the highest priority waiting tasks, up to the number of available decodes.
+    fn wake_waiting(&mut self) {
+        let available = self
+            .max_concurrent_decodes
+            .map_or(self.waiting.len(), |max| max.saturating_sub(self.running));
+        if available == 0 {
+            return;
+        }
+        // Stable, so tasks of the same priority are woken in the order they started waiting
+        self.waiting
+            .sort_by_key(|&(_, priority, _)| Reverse(priority));
+        for (_, _, waker) in self.waiting.iter().take(available) {
+            waker.wake_by_ref();
+        }
+    }
+}
+
+/// A decode task that only runs when the [`DecodeScheduler`] allows it.
+///
+/// Decoders do their work synchronously when polled, until waiting for a frame to be received,
+/// so each poll counts as one decode.
+struct Scheduled<T> {
+    future: Pin<Box<dyn Future<Output = T> + Send>>,
+    state: Arc<Mutex<SchedulerState>>,
+    id: u64,
+    priority: Arc<AtomicI32>,
+}
+
+impl<T> Future for Scheduled<T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let priority = self.priority.load(Ordering::Relaxed);
+        if !self
+            .state
+            .lock()
+            .unwrap()
+            .try_start(self.id, priority, cx.waker())
+        {
+            return Poll::Pending;
+        }
+        let this = &mut *self;
+        let _running = Running(&this.state);
+        this.future.as_mut().poll(cx)
+    }
+}
+
+/// Releases the decode of a running [`Scheduled`] task when dropped,
+/// so a decoder that panics does not hold it forever.
+struct Running<'a>(&'a Mutex<SchedulerState>);
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        let mut state = self.0.lock().unwrap();
+        state.running -= 1;
+        state.wake_waiting();
+    }
+}
+
+impl<T> Drop for Scheduled<T> {
+    fn drop(&mut self) {
+        // Stop waiting, so that lower priority tasks are not blocked
+        let mut state = self.state.lock().unwrap();
+        state.remove_waiting(self.id);
+        state.wake_waiting();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::poll_fn,
+        panic::{self, AssertUnwindSafe},
+        sync::atomic::AtomicUsize,
+        task::Wake,
+    };
+
+    use super::*;
+
+    /// A waker counting how many times it was woken.
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl CountingWaker {
+        fn count(&self) -> usize {
+            self.0.load(Ordering::SeqCst)
+        }
+    }
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.wake_by_ref();
+        }
+
+        fn wake_by_ref(self: &Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// A scheduled task with `priority`, and its waker.
+    struct TestTask<T> {
+        scheduled: Scheduled<T>,
+        waker: Arc<CountingWaker>,
+    }
+
+    impl<T> TestTask<T> {
+        fn new(
+            scheduler: &DecodeScheduler,
+            future: impl Future<Output = T> + Send + 'static,
+            priority: i32,
+        ) -> Self {
+            Self {
+                scheduled: scheduler.schedule(future, Arc::new(AtomicI32::new(priority))),
+                waker: Arc::default(),
+            }
+        }
+
+        fn poll(&mut self) -> Poll<T> {
+            let waker = Waker::from(self.waker.clone());
+            Pin::new(&mut self.scheduled).poll(&mut Context::from_waker(&waker))
+        }
+    }
+
+    fn running(scheduler: &DecodeScheduler) -> usize {
+        scheduler.state.lock().unwrap().running
+    }
+
+    #[test]
+    fn priority_order() {
+        let scheduler = DecodeScheduler::default().with_max_concurrent_decodes(1);
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut tasks: Vec<TestTask<()>> = [0, 10, 5]
+            .into_iter()
+            .map(|priority| {
+                let order = order.clone();
+                TestTask::new(
+                    &scheduler,
+                    async move { order.lock().unwrap().push(priority) },
+                    priority,
+                )
+            })
+            .collect();
+        let [low, high, mid] = &mut tasks[..] else {
+            unreachable!();
+        };
+
+        // Another decode is running
+        scheduler.state.lock().unwrap().running = 1;
+        assert!(low.poll().is_pending());
+        assert!(high.poll().is_pending());
+        assert!(mid.poll().is_pending());
+        // The running decode finishes, only the highest priority task can start
+        drop(Running(&scheduler.state));
+        assert_eq!(
+            (low.waker.count(), high.waker.count(), mid.waker.count()),
+            (0, 1, 0)
+        );
+        // A lower priority task polled meanwhile does not take the decode
+        assert!(low.poll().is_pending());
+        assert!(high.poll().is_ready());
+        assert_eq!(mid.waker.count(), 1);
+        assert!(mid.poll().is_ready());
+        assert_eq!(low.waker.count(), 1);
+        assert!(low.poll().is_ready());
+        assert_eq!(*order.lock().unwrap(), [10, 5, 0]);
+        assert_eq!(running(&scheduler), 0);
+    }
+
+    /// A task that polls `waiting` while running, so that it waits for the decode.
+    fn poll_while_running(
+        waiting: &Arc<Mutex<TestTask<()>>>,
+        panics: bool,
+    ) -> impl Future<Output = ()> + Send + 'static {
+        let waiting = waiting.clone();
+        poll_fn(move |_| {
+            assert!(waiting.lock().unwrap().poll().is_pending());
+            if panics {
+                panic!("decoder panicked");
+            }
+            Poll::Ready(())
+        })
+    }
+
+    #[test]
+    fn wake_when_running_task_completes() {
+        let scheduler = DecodeScheduler::default().with_max_concurrent_decodes(1);
+        let waiting = Arc::new(Mutex::new(TestTask::new(&scheduler, async {}, 0)));
+        let mut running_task = TestTask::new(&scheduler, poll_while_running(&waiting, false), 0);
+
+        assert!(running_task.poll().is_ready());
+        let mut waiting = waiting.lock().unwrap();
+        assert_eq!(waiting.waker.count(), 1);
+        assert_eq!(running(&scheduler), 0);
+        assert!(waiting.poll().is_ready());
+    }
+
+    #[test]
+    fn wake_when_running_task_panics() {
+        let scheduler = DecodeScheduler::default().with_max_concurrent_decodes(1);
+        let waiting = Arc::new(Mutex::new(TestTask::new(&scheduler, async {}, 0)));
+        let mut running_task = TestTask::new(&scheduler, poll_while_running(&waiting, true), 0);
+
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| running_task.poll())).is_err());
+        let mut waiting = waiting.lock().unwrap();
+        assert_eq!(waiting.waker.count(), 1);
+        assert_eq!(running(&scheduler), 0);
+        assert!(waiting.poll().is_ready());
+    }
+
+    #[test]
+    fn wake_when_waiting_task_dropped() {
+        let scheduler = DecodeScheduler::default().with_max_concurrent_decodes(1);
+        let mut high = TestTask::new(&scheduler, async {}, 10);
+        let mut low = TestTask::new(&scheduler, async {}, 0);
+
+        scheduler.state.lock().unwrap().running = 1;
+        assert!(high.poll().is_pending());
+        assert!(low.poll().is_pending());
+        drop(Running(&scheduler.state));
+        assert_eq!(low.waker.count(), 0);
+        // The woken higher priority task is dropped before it starts
+        drop(high);
+        assert_eq!(low.waker.count(), 1);
+        assert!(low.poll().is_ready());
+    }
+}
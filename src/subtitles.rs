@@ -0,0 +1,340 @@
+use std::{fmt::Display, time::Duration};
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    prelude::*,
+};
+
+use crate::video_sink::VideoSink;
+
+/// Subtitles or captions for a video, loaded from a WebVTT (`.vtt`) or SubRip (`.srt`) file.
+///
+/// Display them with a [`VideoSubtitles`] component.
+/// Formatting tags such as `<i>` are removed from the text, cue settings are ignored.
+#[derive(Asset, TypePath, Debug, Clone, Default)]
+pub struct Subtitles {
+    /// The cues, sorted by start time.
+    pub cues: Vec<SubtitleCue>,
+}
+
+/// Text displayed during part of a video.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubtitleCue {
+    /// When the text is displayed, from the start of the video.
+    pub start: Duration,
+    /// When the text stops being displayed, exclusive.
+    pub end: Duration,
+    /// The text, lines are separated by `\n`.
+    pub text: String,
+}
+
+impl Subtitles {
+    /// The text of the cues active at `time`, joined by `\n` if they overlap.
+    pub fn text_at(&self, time: Duration) -> Option<String> {
+        let text: Vec<&str> = self
+            .cues
+            .iter()
+            .take_while(|cue| cue.start <= time)
+            .filter(|cue| time < cue.end)
+            .map(|cue| cue.text.as_str())
+            .collect();
+        (!text.is_empty()).then(|| text.join("\n"))
+    }
+}
+
+/// A component displaying [`Subtitles`] in sync with the [`VideoSink`] on the same entity.
+///
+/// The active text is available from [`VideoSubtitles::text`], and [`SubtitleChanged`]
+/// is triggered on the entity whenever it changes.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_av1::{PlaybackMode, SubtitleChanged, VideoPlayer, VideoSubtitles};
+/// fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+///     commands
+///         .spawn((
+///             VideoPlayer::new(asset_server.load("av1/cutscene.ivf"), PlaybackMode::Remove),
+///             VideoSubtitles::new(asset_server.load("av1/cutscene.en.vtt")),
+///         ))
+///         .observe(|trigger: Trigger<SubtitleChanged>| {
+///             info!("caption: {:?}", trigger.text);
+///         });
+/// }
+/// ```
+#[derive(Component, Debug, Clone)]
+pub struct VideoSubtitles {
+    /// The subtitles to display.
+    pub subtitles: Handle<Subtitles>,
+    text: Option<String>,
+}
+
+/// Triggered on the entity of a [`VideoSubtitles`] when the active subtitle text changes.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct SubtitleChanged {
+    /// The new text, `None` if no subtitle is active.
+    pub text: Option<String>,
+}
+
+impl VideoSubtitles {
+    /// Creates a new [`VideoSubtitles`] displaying `subtitles`.
+    pub fn new(subtitles: Handle<Subtitles>) -> Self {
+        Self {
+            subtitles,
+            text: None,
+        }
+    }
+
+    /// The text of the active subtitle, if any.
+    pub fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+
+    pub(crate) fn update_subtitles(
+        mut query: Query<(Entity, &mut VideoSubtitles, Option<&VideoSink>)>,
+        subtitles: Res<Assets<Subtitles>>,
+        mut commands: Commands,
+    ) {
+        for (entity, mut video_subtitles, sink) in &mut query {
+            let text = sink
+                .and_then(VideoSink::video_position)
+                .zip(subtitles.get(&video_subtitles.subtitles))
+                .and_then(|(position, subtitles)| subtitles.text_at(position));
+            if text != video_subtitles.text {
+                video_subtitles.text = text.clone();
+                commands.trigger_targets(SubtitleChanged { text }, entity);
+            }
+        }
+    }
+}
+
+/// Displays the active subtitle of the [`VideoSubtitles`] of an entity in a UI [`Text`].
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_av1::{PlaybackMode, SubtitleText, VideoPlayer, VideoSubtitles};
+/// fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+///     let video = commands
+///         .spawn((
+///             VideoPlayer::new(asset_server.load("av1/cutscene.ivf"), PlaybackMode::Remove),
+///             VideoSubtitles::new(asset_server.load("av1/cutscene.en.srt")),
+///         ))
+///         .id();
+///     commands.spawn((
+///         SubtitleText(video),
+///         Node {
+///             position_type: PositionType::Absolute,
+///             bottom: Val::Px(32.0),
+///             ..default()
+///         },
+///     ));
+/// }
+/// ```
+#[cfg(feature = "bevy_ui")]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+#[require(Text)]
+pub struct SubtitleText(pub Entity);
+
+#[cfg(feature = "bevy_ui")]
+impl SubtitleText {
+    pub(crate) fn update_text(
+        mut query: Query<(&SubtitleText, &mut Text)>,
+        subtitles: Query<&VideoSubtitles>,
+    ) {
+        for (subtitle_text, mut text) in &mut query {
+            if let Ok(subtitles) = subtitles.get(subtitle_text.0) {
+                let subtitle = subtitles.text().unwrap_or_default();
+                if text.0 != subtitle {
+                    text.0 = subtitle.to_string();
+                }
+            }
+        }
+    }
+}
+
+/// Loads WebVTT and SubRip files as [`Subtitles`] [`Assets`].
+#[derive(Default)]
+pub struct SubtitlesLoader;
+
+/// An error loading [`Subtitles`].
+#[derive(Debug)]
+pub enum SubtitlesLoaderError {
+    Io(std::io::Error),
+    Utf8(std::string::FromUtf8Error),
+    /// A cue timing line could not be parsed.
+    InvalidTiming(String),
+}
+
+impl Display for SubtitlesLoaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl std::error::Error for SubtitlesLoaderError {}
+
+impl AssetLoader for SubtitlesLoader {
+    type Asset = Subtitles;
+    type Settings = ();
+    type Error = SubtitlesLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Subtitles, Self::Error> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(SubtitlesLoaderError::Io)?;
+        let text = String::from_utf8(bytes).map_err(SubtitlesLoaderError::Utf8)?;
+        parse_subtitles(&text)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["vtt", "srt"]
+    }
+}
+
+/// Parse WebVTT or SubRip, which share the same cue layout:
+/// an optional identifier line, a `start --> end` timing line, then text lines up to a blank line.
+fn parse_subtitles(text: &str) -> Result<Subtitles, SubtitlesLoaderError> {
+    let text = text.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let mut cues = Vec::new();
+    for block in text.split("\n\n") {
+        let mut lines = block.lines().skip_while(|line| !line.contains("-->"));
+        // Headers, comments and style blocks have no timing line
+        let Some(timing) = lines.next() else {
+            continue;
+        };
+        let (start, end) = parse_timing(timing)
+            .ok_or_else(|| SubtitlesLoaderError::InvalidTiming(timing.to_string()))?;
+        let text = lines.map(strip_tags).collect::<Vec<_>>().join("\n");
+        cues.push(SubtitleCue { start, end, text });
+    }
+    cues.sort_by_key(|cue| cue.start);
+    Ok(Subtitles { cues })
+}
+
+/// Parse `00:01:02,500 --> 00:01:04,000`, ignoring WebVTT cue settings after the end time.
+fn parse_timing(line: &str) -> Option<(Duration, Duration)> {
+    let (start, end) = line.split_once("-->")?;
+    let end = end.split_whitespace().next()?;
+    Some((parse_timestamp(start.trim())?, parse_timestamp(end)?))
+}
+
+/// Parse `hh:mm:ss.ttt`, or `mm:ss.ttt` in WebVTT, with a `,` decimal separator in SubRip.
+fn parse_timestamp(timestamp: &str) -> Option<Duration> {
+    let (time, millis) = timestamp.split_once(['.', ','])?;
+    let mut seconds: u64 = 0;
+    for part in time.split(':') {
+        // Overflows on malformed files, which must not panic
+        seconds = seconds.checked_mul(60)?.checked_add(part.parse().ok()?)?;
+    }
+    Duration::from_secs(seconds).checked_add(Duration::from_millis(millis.parse().ok()?))
+}
+
+/// Remove formatting tags such as `<i>` or `<v Speaker>`, and decode escaped characters.
+fn strip_tags(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut in_tag = false;
+    for c in line.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => (),
+        }
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", "\u{a0}")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cue(start: u64, end: u64, text: &str) -> SubtitleCue {
+        SubtitleCue {
+            start: Duration::from_millis(start),
+            end: Duration::from_millis(end),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn srt() {
+        let subtitles = parse_subtitles(
+            "1\n00:00:01,500 --> 00:00:03,000\nHello\nworld\n\n2\n01:00:00,000 --> 01:00:01,250\n<i>Bye</i>\n",
+        )
+        .unwrap();
+        assert_eq!(
+            subtitles.cues,
+            [
+                cue(1500, 3000, "Hello\nworld"),
+                cue(3_600_000, 3_601_250, "Bye")
+            ]
+        );
+    }
+
+    #[test]
+    fn vtt() {
+        let subtitles = parse_subtitles(
+            "WEBVTT\n\nNOTE a comment\nspanning lines\n\nSTYLE\n::cue { color: red }\n\n\
+             intro\n00:01.000 --> 00:02.500 align:start position:10%\n<v Roger>Hi</v>\n",
+        )
+        .unwrap();
+        assert_eq!(subtitles.cues, [cue(1000, 2500, "Hi")]);
+    }
+
+    #[test]
+    fn bom_and_crlf() {
+        let subtitles =
+            parse_subtitles("\u{feff}WEBVTT\r\n\r\n00:00.000 --> 00:01.000\r\nOne\r\nTwo\r\n")
+                .unwrap();
+        assert_eq!(subtitles.cues, [cue(0, 1000, "One\nTwo")]);
+    }
+
+    #[test]
+    fn invalid_timing() {
+        assert!(matches!(
+            parse_subtitles("00:00.000 --> soon\nText"),
+            Err(SubtitlesLoaderError::InvalidTiming(_))
+        ));
+        assert_eq!(parse_timestamp("99999999999999999999:00.000"), None);
+        assert_eq!(parse_timestamp("307445734561825860:00:00.000"), None);
+    }
+
+    #[test]
+    fn overlapping_cues() {
+        let subtitles = Subtitles {
+            cues: vec![cue(0, 2000, "First"), cue(1000, 3000, "Second")],
+        };
+        assert_eq!(
+            subtitles.text_at(Duration::from_millis(500)).unwrap(),
+            "First"
+        );
+        assert_eq!(
+            subtitles.text_at(Duration::from_millis(1500)).unwrap(),
+            "First\nSecond"
+        );
+        assert_eq!(
+            subtitles.text_at(Duration::from_millis(2000)).unwrap(),
+            "Second"
+        );
+        assert_eq!(subtitles.text_at(Duration::from_millis(3000)), None);
+    }
+
+    #[test]
+    fn entities() {
+        assert_eq!(
+            strip_tags("<b>Fish &amp; chips</b> &lt;3&gt;&nbsp;!"),
+            "Fish & chips <3>\u{a0}!"
+        );
+        // Decoded once, not twice
+        assert_eq!(strip_tags("&amp;lt;"), "&lt;");
+    }
+}
@@ -1,19 +1,96 @@
 use crate::{
     PlaybackMode,
-    decodable::{Decodable, Decoder},
-    video::VideoPlayer,
-    video_sink::{DrainVideoSink, VideoFrameUpdated, VideoSink},
+    chapter::Chapter,
+    cue::{CuePoint, CuePointReached},
+    decodable::{Decodable, DecodeSettings, Decoder, VideoFrame},
+    scheduler::{DecodePriority, DecodeScheduler},
+    throttle::{DecodeThrottle, OffscreenPolicy},
+    video::{VideoPlayer, VideoRange, VideoTime},
+    video_sink::{DrainVideoSink, VideoFrameUpdated, VideoResized, VideoSink},
 };
 use bevy::{
     asset::RenderAssetUsages,
     prelude::*,
     render::render_resource::{Extent3d, TextureDimension, TextureFormat},
-    tasks::ComputeTaskPool,
+    tasks::Task,
 };
+use std::{
+    sync::{Arc, atomic::AtomicI32},
+    time::Duration,
+};
+
+/// A decode task that has been started, with the properties of its video.
+pub(crate) struct DecodeTask {
+    pub frame_duration: Duration,
+    pub width: u32,
+    pub height: u32,
+    pub display_aspect_ratio: f32,
+    pub frame_count: Option<u64>,
+    pub duration: Option<Duration>,
+    /// Start of the range to play.
+    pub start: Duration,
+    /// End of the range to play.
+    pub end: Option<Duration>,
+    /// Where playback restarts at the end of the range, if looping.
+    pub loop_start: Duration,
+    pub looping: bool,
+    /// The cue points of the video, sorted by time.
+    pub cues: Vec<CuePoint>,
+    /// The chapters of the video, sorted by start time.
+    pub chapters: Vec<Chapter>,
+    pub rx: async_channel::Receiver<VideoFrame>,
+    pub task: Task<Result<()>>,
+}
+
+/// Start decoding `source` on the `scheduler`.
+pub(crate) fn start_decode_task<Source: Decodable>(
+    source: &Source,
+    settings: &DecodeSettings,
+    loop_: bool,
+    range: VideoRange,
+    throttle: DecodeThrottle,
+    priority: Arc<AtomicI32>,
+    scheduler: &DecodeScheduler,
+) -> DecodeTask {
+    let mut decoder = source.decoder(settings);
+    decoder.set_throttle(throttle);
+    decoder.set_range(range);
+    let frame_duration = decoder.frame_duration();
+    let width = decoder.width();
+    let height = decoder.height();
+    let display_aspect_ratio = decoder.display_aspect_ratio();
+    let frame_count = decoder.frame_count();
+    let duration = decoder.duration();
+    let (tx, rx) = async_channel::bounded(1); //XXX make configurable?
+    let task = scheduler.spawn(async move { decoder.decode(tx, loop_).await }, priority);
+    let start = range
+        .start
+        .map_or(Duration::ZERO, |start| start.to_duration(frame_duration));
+    DecodeTask {
+        frame_duration,
+        width,
+        height,
+        display_aspect_ratio,
+        frame_count,
+        duration,
+        start,
+        end: range.end.map(|end| end.to_duration(frame_duration)),
+        loop_start: range
+            .loop_start
+            .map_or(start, |loop_start| loop_start.to_duration(frame_duration)),
+        looping: loop_,
+        cues: source.cue_points().to_vec(),
+        chapters: source.chapters().to_vec(),
+        rx,
+        task,
+    }
+}
 
 pub fn play_videos<Source: Asset + Decodable>(
     query_nonplaying: Query<(Entity, &VideoPlayer<Source>), Without<VideoSink>>,
+    priorities: Query<&DecodePriority>,
     video_sources: Res<Assets<Source>>,
+    scheduler: Res<DecodeScheduler>,
     mut images: ResMut<Assets<Image>>,
     mut commands: Commands,
 ) {
@@ -21,28 +98,139 @@ pub fn play_videos<Source: Asset + Decodable>(
         let Some(video_source) = video_sources.get(&player.source) else {
             continue;
         };
-        let mut decoder = video_source.decoder();
-        let timebase = decoder.timebase();
-        let width = decoder.width();
-        let height = decoder.height();
+        let throttle = DecodeThrottle::default();
+        let priority = priorities.get(entity).copied().unwrap_or_default();
+        let priority = Arc::new(AtomicI32::new(priority.0));
+        let loop_ = matches!(player.mode, PlaybackMode::Loop);
+        let decode_task = start_decode_task(
+            video_source,
+            &player.settings,
+            loop_,
+            player.range,
+            throttle.clone(),
+            priority.clone(),
+            &scheduler,
+        );
         let image = Image::new_uninit(
             Extent3d {
-                width,
-                height,
+                width: decode_task.width,
+                height: decode_task.height,
                 ..default()
             },
             TextureDimension::D2,
             TextureFormat::Rgba8Unorm,
             RenderAssetUsages::default(),
         );
-        let loop_ = matches!(player.mode, PlaybackMode::Loop);
-        let (tx, rx) = async_channel::bounded(1); //XXX make configurable?
-        let task = ComputeTaskPool::get().spawn(async move { decoder.decode(tx, loop_).await });
-        let sink = VideoSink::new(images.add(image), timebase, width, height, rx, task);
+        let sink = VideoSink::new(images.add(image), decode_task)
+            .with_throttle(throttle)
+            .with_priority(priority);
         commands.entity(entity).insert(sink);
     }
 }
 
+/// Restart decoding of sinks that were seeked, see [`VideoSink::seek`].
+pub fn seek_video_sinks<Source: Asset + Decodable>(
+    mut query_playing: Query<(Entity, &mut VideoSink, &VideoPlayer<Source>)>,
+    video_sources: Res<Assets<Source>>,
+    scheduler: Res<DecodeScheduler>,
+    mut commands: Commands,
+) {
+    for (entity, mut sink, player) in &mut query_playing {
+        let Some(mut position) = sink.take_seek() else {
+            continue;
+        };
+        let Some(video_source) = video_sources.get(&player.source) else {
+            continue;
+        };
+        // A reloaded video may now end before the position
+        if video_source
+            .duration()
+            .is_some_and(|duration| position >= duration)
+        {
+            position = sink.range_start();
+        }
+        let range = VideoRange {
+            start: Some(VideoTime::Time(position)),
+            // Loops still restart at the start of the range
+            loop_start: player.range.loop_start.or(player.range.start),
+            ..player.range
+        };
+        let decode_task = start_decode_task(
+            video_source,
+            &player.settings,
+            matches!(player.mode, PlaybackMode::Loop),
+            range,
+            sink.throttle().clone(),
+            sink.priority().clone(),
+            &scheduler,
+        );
+        sink.restart(decode_task, position);
+        commands.entity(entity).remove::<DrainVideoSink>();
+    }
+}
+
+/// Restart playing videos whose source was modified, e.g. by hot reloading,
+/// from the same position.
+pub fn reload_video_sinks<Source: Asset + Decodable>(
+    mut query_playing: Query<(&mut VideoSink, &VideoPlayer<Source>)>,
+    mut source_events: EventReader<AssetEvent<Source>>,
+) {
+    let modified: Vec<AssetId<Source>> = source_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+    if modified.is_empty() {
+        return;
+    }
+    for (mut sink, player) in &mut query_playing {
+        if modified.contains(&player.source.id()) {
+            let position = sink.position();
+            sink.seek(position);
+        }
+    }
+}
+
+pub fn throttle_offscreen_videos(
+    mut query_playing: Query<(&mut VideoSink, &OffscreenPolicy, &ViewVisibility)>,
+    mut query_unthrottled: Query<&mut VideoSink, Without<OffscreenPolicy>>,
+    mut removed: RemovedComponents<OffscreenPolicy>,
+    time: Res<Time>,
+) {
+    // Otherwise a sink paused offscreen when its policy is removed would stay paused
+    for entity in removed.read() {
+        if let Ok(mut sink) = query_unthrottled.get_mut(entity) {
+            sink.set_throttle(OffscreenPolicy::Continue, time.elapsed());
+        }
+    }
+    for (mut sink, policy, visibility) in &mut query_playing {
+        let policy = if visibility.get() {
+            OffscreenPolicy::Continue
+        } else {
+            *policy
+        };
+        sink.set_throttle(policy, time.elapsed());
+    }
+}
+
+pub fn update_decode_priorities(
+    query_playing: Query<(&VideoSink, &DecodePriority), Changed<DecodePriority>>,
+    query_default: Query<&VideoSink, Without<DecodePriority>>,
+    mut removed: RemovedComponents<DecodePriority>,
+) {
+    // Otherwise a sink would keep the priority that was removed
+    for entity in removed.read() {
+        if let Ok(sink) = query_default.get(entity) {
+            sink.set_priority(DecodePriority::default());
+        }
+    }
+    for (sink, priority) in &query_playing {
+        sink.set_priority(*priority);
+    }
+}
+
 pub fn poll_video_sinks(
     mut query_playing: Query<(Entity, &mut VideoSink), Without<DrainVideoSink>>,
     mut commands: Commands,
@@ -70,10 +258,27 @@ pub fn render_video_sinks<Source: Asset + Decodable>(
     mut video_frame_events: EventWriter<VideoFrameUpdated>,
 ) {
     for (entity, mut sink, player, drain) in &mut query_playing {
-        match sink.next_frame(time.elapsed()) {
+        let mut previous_position = sink.video_position();
+        let mut frame = sink.next_frame(time.elapsed());
+        // The decoder finished, or playback reached the end of the range
+        let mut finished = drain.is_some() || sink.is_ended();
+        // Continue with the next queued video without showing a blank frame
+        if frame.is_none() && finished && !sink.is_paused() && sink.play_queued() {
+            commands.entity(entity).remove::<DrainVideoSink>();
+            // The queued video just started, even if its first frame is not decoded yet
+            finished = false;
+            previous_position = None;
+            frame = sink.next_frame(time.elapsed());
+        }
+        if frame.is_some() {
+            for cue in sink.crossed_cue_points(previous_position) {
+                commands.trigger_targets(CuePointReached::from(cue), entity);
+            }
+        }
+        match frame {
             None => {
-                // If draining and no more frames, tear down
-                if drain.is_some() {
+                // If finished and no more frames, tear down
+                if finished && !sink.is_paused() && !sink.is_held() {
                     match player.mode {
                         PlaybackMode::Remove => {
                             commands
@@ -91,11 +296,116 @@ pub fn render_video_sinks<Source: Asset + Decodable>(
                 }
             }
             Some(frame) => {
-                if let Some(image) = images.get_mut(sink.image()) {
+                let resized = sink.resize(frame.image.size());
+                // Uninitialized when only YUV planes are decoded
+                if frame.image.data.is_some()
+                    && let Some(image) = images.get_mut(sink.image())
+                {
+                    sink.record_upload(frame.image.data.as_ref().map_or(0, Vec::len));
                     *image = frame.image;
                     video_frame_events.write(VideoFrameUpdated(sink.image().id()));
                 }
+                sink.set_frame_info(frame.info);
+                if resized {
+                    commands.trigger_targets(
+                        VideoResized {
+                            width: sink.width(),
+                            height: sink.height(),
+                            display_aspect_ratio: sink.display_aspect_ratio(),
+                        },
+                        entity,
+                    );
+                }
+                sink.yuv_planes = frame.planes;
             }
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::VideoSource;
+    use bevy::tasks::{AsyncComputeTaskPool, TaskPool, futures_lite::future};
+
+    /// A decode task that never finishes, receiving frames from `rx`.
+    pub(crate) fn pending_decode_task(rx: async_channel::Receiver<VideoFrame>) -> DecodeTask {
+        let task = AsyncComputeTaskPool::get_or_init(TaskPool::new).spawn(future::pending());
+        DecodeTask {
+            frame_duration: Duration::from_millis(40),
+            width: 2,
+            height: 2,
+            display_aspect_ratio: 1.0,
+            frame_count: None,
+            duration: None,
+            start: Duration::ZERO,
+            end: None,
+            loop_start: Duration::ZERO,
+            looping: false,
+            cues: Vec::new(),
+            chapters: Vec::new(),
+            rx,
+            task,
+        }
+    }
+
+    #[test]
+    fn queued_video_without_frame_is_not_removed() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<Assets<Image>>()
+            .add_event::<VideoFrameUpdated>()
+            .add_systems(Update, render_video_sinks::<VideoSource>);
+        let (_tx, rx) = async_channel::bounded(1);
+        // The queued decode task has not produced its first frame
+        let (_queued_tx, queued_rx) = async_channel::bounded(1);
+        let mut sink = VideoSink::new(Handle::default(), pending_decode_task(rx));
+        sink.queue(pending_decode_task(queued_rx));
+        let entity = app
+            .world_mut()
+            .spawn((
+                sink,
+                VideoPlayer::new(Handle::default(), PlaybackMode::Remove),
+                DrainVideoSink,
+            ))
+            .id();
+
+        app.update();
+
+        let entity = app.world().entity(entity);
+        assert!(!entity.contains::<DrainVideoSink>());
+        assert!(entity.contains::<VideoPlayer>());
+        let sink = entity.get::<VideoSink>().expect("sink removed");
+        assert!(!sink.has_queued());
+    }
+
+    #[test]
+    fn removed_priority_is_reset() {
+        let mut app = App::new();
+        app.add_systems(Update, update_decode_priorities);
+        let (_tx, rx) = async_channel::bounded(1);
+        let entity = app
+            .world_mut()
+            .spawn((
+                VideoSink::new(Handle::default(), pending_decode_task(rx)),
+                DecodePriority(5),
+            ))
+            .id();
+        let priority = |app: &App| {
+            app.world()
+                .get::<VideoSink>(entity)
+                .unwrap()
+                .priority()
+                .load(std::sync::atomic::Ordering::Relaxed)
+        };
+
+        app.update();
+        assert_eq!(priority(&app), 5);
+
+        app.world_mut()
+            .entity_mut(entity)
+            .remove::<DecodePriority>();
+        app.update();
+        assert_eq!(priority(&app), DecodePriority::default().0);
+    }
+}
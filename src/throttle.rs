@@ -0,0 +1,55 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU32, Ordering},
+};
+
+use bevy::prelude::*;
+
+/// How a video is decoded while its entity is not visible in any view.
+///
+/// Insert this component next to a [`VideoPlayer`][crate::VideoPlayer] on an entity
+/// with a mesh or sprite, visibility is determined by its [`ViewVisibility`].
+/// When the entity becomes visible again, decoding returns to full rate.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OffscreenPolicy {
+    /// Keep decoding at full rate.
+    #[default]
+    Continue,
+    /// Stop decoding, playback resumes from the same frame when visible.
+    Pause,
+    /// Only decode key frames, playback stays in sync.
+    /// When visible, full rate decoding resumes at the next key frame.
+    KeyframesOnly,
+    /// Only output one of every `n` frames, playback stays in sync.
+    /// Frames are still decoded, but are not converted or uploaded.
+    ReducedRate(u32),
+}
+
+/// A handle shared between a [`VideoSink`][crate::VideoSink] and its [`Decoder`][crate::Decoder],
+/// holding the [`OffscreenPolicy`] currently in effect.
+///
+/// The policy is [`OffscreenPolicy::Continue`] while the video is visible.
+#[derive(Debug, Default, Clone)]
+pub struct DecodeThrottle(Arc<AtomicU32>);
+
+impl DecodeThrottle {
+    /// The policy currently in effect.
+    pub fn get(&self) -> OffscreenPolicy {
+        match self.0.load(Ordering::Relaxed) {
+            0 => OffscreenPolicy::Continue,
+            1 => OffscreenPolicy::Pause,
+            2 => OffscreenPolicy::KeyframesOnly,
+            n => OffscreenPolicy::ReducedRate(n - 2),
+        }
+    }
+
+    pub(crate) fn set(&self, policy: OffscreenPolicy) {
+        let value = match policy {
+            OffscreenPolicy::Continue => 0,
+            OffscreenPolicy::Pause => 1,
+            OffscreenPolicy::KeyframesOnly => 2,
+            OffscreenPolicy::ReducedRate(n) => n.max(1).saturating_add(2),
+        };
+        self.0.store(value, Ordering::Relaxed);
+    }
+}
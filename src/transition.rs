@@ -0,0 +1,473 @@
+use std::time::Duration;
+
+use bevy::{
+    asset::{RenderAssetUsages, load_internal_asset, weak_handle},
+    core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+    image::BevyDefault as _,
+    prelude::*,
+    render::{
+        Extract, ExtractSchedule, Render, RenderApp, RenderSet,
+        graph::CameraDriverLabel,
+        render_asset::RenderAssets,
+        render_graph::{self, NodeRunError, RenderGraph, RenderGraphContext, RenderLabel},
+        render_resource::{
+            binding_types::{sampler, texture_2d, uniform_buffer},
+            *,
+        },
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        texture::GpuImage,
+    },
+};
+
+use crate::video_sink::{VideoFrameUpdated, VideoSink};
+use uniform::TransitionUniform;
+
+const TRANSITION_SHADER_HANDLE: Handle<Shader> =
+    weak_handle!("5d0e8f3a-7c1b-4f2e-9a6d-3b8c1e4f7a20");
+
+/// Blends the video of one [`VideoSink`] into another, into a single output [`Image`].
+///
+/// Display [`VideoTransition::image`] instead of the images of the sinks. Once the transition
+/// finishes the output shows the `to` video, call [`VideoTransition::transition_to`]
+/// to transition to another video into the same output image.
+/// The output is rendered on the GPU every frame during the transition, then only when
+/// the `to` video displays a new frame.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_av1::{TransitionKind, VideoTransition};
+/// # use std::time::Duration;
+/// fn switch_camera_angle(
+///     keys: Res<ButtonInput<KeyCode>>,
+///     mut transitions: Query<&mut VideoTransition>,
+///     angles: Query<Entity, With<CameraAngle>>,
+/// ) {
+///     if !keys.just_pressed(KeyCode::Space) {
+///         return;
+///     }
+///     let Ok(mut transition) = transitions.single_mut() else {
+///         return;
+///     };
+///     let current = transition.to;
+///     if let Some(next) = angles.iter().find(|&angle| angle != current) {
+///         transition.transition_to(next, TransitionKind::Crossfade, Duration::from_secs_f32(0.5));
+///     }
+/// }
+/// # #[derive(Component)]
+/// # struct CameraAngle;
+/// ```
+#[derive(Component, Debug, Clone)]
+pub struct VideoTransition {
+    /// The entity of the [`VideoSink`] to transition from.
+    pub from: Entity,
+    /// The entity of the [`VideoSink`] to transition to.
+    pub to: Entity,
+    /// How the videos are blended.
+    pub kind: TransitionKind,
+    /// The duration of the transition.
+    pub duration: Duration,
+    /// Time since the transition started.
+    pub elapsed: Duration,
+    /// The output image the videos are blended into.
+    pub image: Handle<Image>,
+    /// The images to blend into the output this frame, if any.
+    blend: Option<BlendImages>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BlendImages {
+    from: Option<AssetId<Image>>,
+    to: AssetId<Image>,
+}
+
+/// How a [`VideoTransition`] blends two videos.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionKind {
+    /// Blend the videos.
+    #[default]
+    Crossfade,
+    /// Reveal the `to` video from one edge.
+    Wipe(WipeDirection),
+    /// Fade the `from` video to black, then fade in the `to` video.
+    DipToBlack,
+}
+
+/// The direction a [`TransitionKind::Wipe`] reveals the `to` video.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum WipeDirection {
+    /// From the left edge to the right edge.
+    #[default]
+    LeftToRight,
+    /// From the right edge to the left edge.
+    RightToLeft,
+    /// From the top edge to the bottom edge.
+    TopToBottom,
+    /// From the bottom edge to the top edge.
+    BottomToTop,
+}
+
+impl VideoTransition {
+    /// Creates a new [`VideoTransition`] from the video of `from` to the video of `to`,
+    /// blended into `image`, e.g. `images.add(Image::default())`.
+    /// The image is replaced by a render target the size of the `to` video,
+    /// so it has no data in the main world.
+    pub fn new(
+        image: Handle<Image>,
+        from: Entity,
+        to: Entity,
+        kind: TransitionKind,
+        duration: Duration,
+    ) -> Self {
+        Self {
+            from,
+            to,
+            kind,
+            duration,
+            elapsed: Duration::ZERO,
+            image,
+            blend: None,
+        }
+    }
+
+    /// Start a new transition from the current `to` video to the video of `to`.
+    pub fn transition_to(&mut self, to: Entity, kind: TransitionKind, duration: Duration) {
+        self.from = self.to;
+        self.to = to;
+        self.kind = kind;
+        self.duration = duration;
+        self.elapsed = Duration::ZERO;
+    }
+
+    /// The progress of the transition, from `0.0` to `1.0`.
+    pub fn progress(&self) -> f32 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+        (self.elapsed.as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+    }
+
+    /// Whether the transition has finished, the output then only shows the `to` video.
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    pub(crate) fn update_transitions(
+        mut transitions: Query<&mut VideoTransition>,
+        sinks: Query<&VideoSink>,
+        mut images: ResMut<Assets<Image>>,
+        mut frame_events: ParamSet<(
+            EventReader<VideoFrameUpdated>,
+            EventWriter<VideoFrameUpdated>,
+        )>,
+        time: Res<Time>,
+    ) {
+        // Images of the frames displayed by video sinks this frame
+        let updated: Vec<AssetId<Image>> = frame_events.p0().read().map(|event| event.0).collect();
+        for mut transition in &mut transitions {
+            let was_finished = transition.is_finished();
+            transition.elapsed = (transition.elapsed + time.delta()).min(transition.duration);
+            transition.blend = None;
+            let Ok(to) = sinks.get(transition.to) else {
+                continue;
+            };
+            // Once finished, only update when the `to` video does
+            if was_finished && !updated.contains(&to.image().id()) {
+                continue;
+            }
+            // Uninitialized until the first frame is decoded
+            let Some(size) = images
+                .get(to.image())
+                .filter(|image| image.data.is_some())
+                .map(Image::size)
+            else {
+                continue;
+            };
+            let from = sinks
+                .get(transition.from)
+                .ok()
+                .filter(|_| !transition.is_finished())
+                .map(|from| from.image().id());
+            // Modifying the output image recreates its texture, so only replace it
+            // when it can't be rendered to at the size of the `to` video
+            if images.get(&transition.image).is_none_or(|output| {
+                output.size() != size
+                    || !output
+                        .texture_descriptor
+                        .usage
+                        .contains(TextureUsages::RENDER_ATTACHMENT)
+            }) {
+                images.insert(&transition.image, Self::output_image(size));
+                frame_events
+                    .p1()
+                    .write(VideoFrameUpdated(transition.image.id()));
+            }
+            transition.blend = Some(BlendImages {
+                from,
+                to: to.image().id(),
+            });
+        }
+    }
+
+    /// An image of `size` the transition is rendered into.
+    fn output_image(size: UVec2) -> Image {
+        let mut image = Image::new_uninit(
+            Extent3d {
+                width: size.x,
+                height: size.y,
+                ..default()
+            },
+            TextureDimension::D2,
+            TextureFormat::bevy_default(),
+            RenderAssetUsages::default(),
+        );
+        image.texture_descriptor.usage |= TextureUsages::RENDER_ATTACHMENT;
+        image
+    }
+
+    /// The uniform of the shader blending the videos.
+    fn uniform(&self) -> TransitionUniform {
+        let (kind, direction) = match self.kind {
+            TransitionKind::Crossfade => (0, 0),
+            TransitionKind::Wipe(direction) => (1, direction as u32),
+            TransitionKind::DipToBlack => (2, 0),
+        };
+        TransitionUniform {
+            progress: self.progress(),
+            kind,
+            direction,
+        }
+    }
+}
+
+/// Renders [`VideoTransition`]s into their output images on the GPU.
+pub(crate) struct VideoTransitionPlugin;
+
+impl Plugin for VideoTransitionPlugin {
+    fn build(&self, app: &mut App) {
+        if app.get_sub_app(RenderApp).is_none() {
+            return;
+        }
+        load_internal_asset!(
+            app,
+            TRANSITION_SHADER_HANDLE,
+            "transition.wgsl",
+            Shader::from_wgsl
+        );
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .init_resource::<ExtractedTransitions>()
+            .init_resource::<TransitionBindGroups>()
+            .add_systems(ExtractSchedule, extract_transitions)
+            .add_systems(
+                Render,
+                prepare_transition_bind_groups.in_set(RenderSet::PrepareBindGroups),
+            );
+        let mut render_graph = render_app.world_mut().resource_mut::<RenderGraph>();
+        render_graph.add_node(TransitionLabel, TransitionNode);
+        // Render before cameras, which may display the output images
+        render_graph.add_node_edge(TransitionLabel, CameraDriverLabel);
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<TransitionPipeline>();
+    }
+}
+
+// In a module to allow the unused checks generated by the derive
+#[allow(dead_code)]
+mod uniform {
+    use bevy::render::render_resource::ShaderType;
+
+    #[derive(ShaderType, Debug, Clone, Copy)]
+    pub(super) struct TransitionUniform {
+        pub progress: f32,
+        /// `0` crossfade, `1` wipe, `2` dip to black.
+        pub kind: u32,
+        /// The [`WipeDirection`][super::WipeDirection] of a wipe.
+        pub direction: u32,
+    }
+}
+
+/// A transition to render this frame.
+struct ExtractedTransition {
+    output: AssetId<Image>,
+    from: Option<AssetId<Image>>,
+    to: AssetId<Image>,
+    uniform: TransitionUniform,
+}
+
+#[derive(Resource, Default)]
+struct ExtractedTransitions(Vec<ExtractedTransition>);
+
+/// The bind group of each transition to render this frame, with its output image.
+#[derive(Resource, Default)]
+struct TransitionBindGroups(Vec<(AssetId<Image>, BindGroup)>);
+
+#[derive(Resource)]
+struct TransitionPipeline {
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    pipeline_id: CachedRenderPipelineId,
+}
+
+impl FromWorld for TransitionPipeline {
+    fn from_world(render_world: &mut World) -> Self {
+        let render_device = render_world.resource::<RenderDevice>();
+        let layout = render_device.create_bind_group_layout(
+            "video_transition_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                    uniform_buffer::<TransitionUniform>(false),
+                ),
+            ),
+        );
+        // Linear sampling if the videos differ in size
+        let sampler = render_device.create_sampler(&SamplerDescriptor {
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..default()
+        });
+        let pipeline_id = render_world
+            .resource::<PipelineCache>()
+            .queue_render_pipeline(RenderPipelineDescriptor {
+                label: Some("video_transition_pipeline".into()),
+                layout: vec![layout.clone()],
+                vertex: fullscreen_shader_vertex_state(),
+                fragment: Some(FragmentState {
+                    shader: TRANSITION_SHADER_HANDLE,
+                    shader_defs: Vec::new(),
+                    entry_point: "fragment".into(),
+                    targets: vec![Some(ColorTargetState {
+                        format: TextureFormat::bevy_default(),
+                        blend: None,
+                        write_mask: ColorWrites::ALL,
+                    })],
+                }),
+                primitive: PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: MultisampleState::default(),
+                push_constant_ranges: Vec::new(),
+                zero_initialize_workgroup_memory: false,
+            });
+        Self {
+            layout,
+            sampler,
+            pipeline_id,
+        }
+    }
+}
+
+fn extract_transitions(
+    mut extracted: ResMut<ExtractedTransitions>,
+    transitions: Extract<Query<&VideoTransition>>,
+) {
+    extracted.0.clear();
+    extracted
+        .0
+        .extend(transitions.iter().filter_map(|transition| {
+            let blend = transition.blend?;
+            Some(ExtractedTransition {
+                output: transition.image.id(),
+                from: blend.from,
+                to: blend.to,
+                uniform: transition.uniform(),
+            })
+        }));
+}
+
+fn prepare_transition_bind_groups(
+    mut bind_groups: ResMut<TransitionBindGroups>,
+    transitions: Res<ExtractedTransitions>,
+    pipeline: Res<TransitionPipeline>,
+    gpu_images: Res<RenderAssets<GpuImage>>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    bind_groups.0.clear();
+    for transition in &transitions.0 {
+        let Some(to) = gpu_images.get(transition.to) else {
+            continue;
+        };
+        // Without the `from` video, only show the `to` video
+        let (from, progress) = match transition.from.and_then(|from| gpu_images.get(from)) {
+            Some(from) => (from, transition.uniform.progress),
+            None => (to, 1.0),
+        };
+        let mut uniform = UniformBuffer::from(TransitionUniform {
+            progress,
+            ..transition.uniform
+        });
+        uniform.write_buffer(&render_device, &render_queue);
+        let Some(uniform) = uniform.binding() else {
+            continue;
+        };
+        let bind_group = render_device.create_bind_group(
+            "video_transition_bind_group",
+            &pipeline.layout,
+            &BindGroupEntries::sequential((
+                &from.texture_view,
+                &to.texture_view,
+                &pipeline.sampler,
+                uniform,
+            )),
+        );
+        bind_groups.0.push((transition.output, bind_group));
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct TransitionLabel;
+
+struct TransitionNode;
+
+impl render_graph::Node for TransitionNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let bind_groups = world.resource::<TransitionBindGroups>();
+        if bind_groups.0.is_empty() {
+            return Ok(());
+        }
+        let pipeline_id = world.resource::<TransitionPipeline>().pipeline_id;
+        let Some(pipeline) = world
+            .resource::<PipelineCache>()
+            .get_render_pipeline(pipeline_id)
+        else {
+            return Ok(());
+        };
+        let gpu_images = world.resource::<RenderAssets<GpuImage>>();
+        for (output, bind_group) in &bind_groups.0 {
+            let Some(output) = gpu_images.get(*output) else {
+                continue;
+            };
+            let mut render_pass =
+                render_context
+                    .command_encoder()
+                    .begin_render_pass(&RenderPassDescriptor {
+                        label: Some("video_transition_pass"),
+                        color_attachments: &[Some(RenderPassColorAttachment {
+                            view: &output.texture_view,
+                            resolve_target: None,
+                            ops: Operations::default(),
+                        })],
+                        depth_stencil_attachment: None,
+                        timestamp_writes: None,
+                        occlusion_query_set: None,
+                    });
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+struct Transition {
+    progress: f32,
+    // 0 crossfade, 1 wipe, 2 dip to black
+    kind: u32,
+    // 0 left to right, 1 right to left, 2 top to bottom, 3 bottom to top
+    direction: u32,
+}
+
+@group(0) @binding(0) var from_texture: texture_2d<f32>;
+@group(0) @binding(1) var to_texture: texture_2d<f32>;
+@group(0) @binding(2) var texture_sampler: sampler;
+@group(0) @binding(3) var<uniform> transition: Transition;
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let from_color = textureSample(from_texture, texture_sampler, in.uv);
+    let to_color = textureSample(to_texture, texture_sampler, in.uv);
+    let progress = transition.progress;
+    switch transition.kind {
+        // Wipe
+        case 1u: {
+            var revealed: bool;
+            switch transition.direction {
+                case 0u: { revealed = in.uv.x < progress; }
+                case 1u: { revealed = in.uv.x >= 1.0 - progress; }
+                case 2u: { revealed = in.uv.y < progress; }
+                default: { revealed = in.uv.y >= 1.0 - progress; }
+            }
+            return select(from_color, to_color, revealed);
+        }
+        // Dip to black, alpha always crossfades
+        case 2u: {
+            let alpha = mix(from_color.a, to_color.a, progress);
+            if progress < 0.5 {
+                return vec4(from_color.rgb * (1.0 - progress * 2.0), alpha);
+            }
+            return vec4(to_color.rgb * (progress * 2.0 - 1.0), alpha);
+        }
+        // Crossfade
+        default: {
+            return mix(from_color, to_color, progress);
+        }
+    }
+}
@@ -1,6 +1,11 @@
+use std::time::Duration;
+
 use bevy::prelude::*;
 
-use crate::{decodable::Decodable, video_source::VideoSource};
+use crate::{
+    decodable::{Decodable, DecodeSettings},
+    video_source::VideoSource,
+};
 
 /// A component for playing a video.
 ///
@@ -12,7 +17,7 @@ use crate::{decodable::Decodable, video_source::VideoSource};
 /// When Bevy begins the video playback, a [`VideoSink`][crate::VideoSink] component will be
 /// added to the entity. You can use that component to access the video dimensions and texture image.
 ///
-#[derive(Component, Clone, Default)]
+#[derive(Component, Clone)]
 pub struct VideoPlayer<Source = VideoSource>
 where
     Source: Asset + Decodable,
@@ -21,6 +26,22 @@ where
     pub source: Handle<Source>,
     /// The video playback mode.
     pub mode: PlaybackMode,
+    /// Settings used to decode the video.
+    pub settings: DecodeSettings,
+    /// The part of the video to play.
+    pub range: VideoRange,
+}
+
+// Not derived, which would require `Source: Default`
+impl<Source: Asset + Decodable> Default for VideoPlayer<Source> {
+    fn default() -> Self {
+        Self {
+            source: Handle::default(),
+            mode: PlaybackMode::default(),
+            settings: DecodeSettings::default(),
+            range: VideoRange::default(),
+        }
+    }
 }
 
 impl VideoPlayer<VideoSource> {
@@ -30,7 +51,93 @@ impl VideoPlayer<VideoSource> {
     /// initialize an [`VideoPlayer`] with a different type, just initialize it directly using normal
     /// struct syntax.
     pub fn new(source: Handle<VideoSource>, mode: PlaybackMode) -> Self {
-        Self { source, mode }
+        Self {
+            source,
+            mode,
+            settings: DecodeSettings::default(),
+            range: VideoRange::default(),
+        }
+    }
+
+    /// Sets the [`DecodeSettings`] used to decode the video.
+    pub fn with_settings(mut self, settings: DecodeSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Sets the [`VideoRange`] of the video to play.
+    pub fn with_range(mut self, range: VideoRange) -> Self {
+        self.range = range;
+        self
+    }
+}
+
+/// A position in a video.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VideoTime {
+    /// A time from the start of the video.
+    Time(Duration),
+    /// A frame number, counting from `0` at the start of the video.
+    Frame(u64),
+}
+
+impl VideoTime {
+    /// The time from the start of a video with frames of `frame_duration`,
+    /// see [`Decoder::frame_duration`][crate::Decoder::frame_duration].
+    pub fn to_duration(self, frame_duration: Duration) -> Duration {
+        match self {
+            Self::Time(time) => time,
+            Self::Frame(frame) => frame_duration.mul_f64(frame as f64),
+        }
+    }
+}
+
+impl From<Duration> for VideoTime {
+    fn from(time: Duration) -> Self {
+        Self::Time(time)
+    }
+}
+
+/// The part of a video to play, so one video can contain several clips.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_av1::{PlaybackMode, VideoPlayer, VideoRange, VideoTime};
+/// fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+///     // Play an intro from frame 0 to 47, then loop frames 24 to 47
+///     commands.spawn(
+///         VideoPlayer::new(asset_server.load("av1/character.ivf"), PlaybackMode::Loop)
+///             .with_range(
+///                 VideoRange::new(VideoTime::Frame(0), VideoTime::Frame(48))
+///                     .with_loop_start(VideoTime::Frame(24)),
+///             ),
+///     );
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VideoRange {
+    /// Where playback starts, defaults to the start of the video.
+    pub start: Option<VideoTime>,
+    /// Where playback ends, exclusive, defaults to the end of the video.
+    pub end: Option<VideoTime>,
+    /// Where playback restarts at the end with [`PlaybackMode::Loop`], defaults to [`Self::start`].
+    pub loop_start: Option<VideoTime>,
+}
+
+impl VideoRange {
+    /// Creates a new [`VideoRange`] from `start` up to `end`.
+    pub fn new(start: impl Into<VideoTime>, end: impl Into<VideoTime>) -> Self {
+        Self {
+            start: Some(start.into()),
+            end: Some(end.into()),
+            loop_start: None,
+        }
+    }
+
+    /// Sets where playback restarts at the end with [`PlaybackMode::Loop`].
+    pub fn with_loop_start(mut self, loop_start: impl Into<VideoTime>) -> Self {
+        self.loop_start = Some(loop_start.into());
+        self
     }
 }
 
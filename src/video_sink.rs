@@ -1,11 +1,30 @@
-use std::time::Duration;
+use std::{
+    ops::{
+        Bound::{self, Excluded, Included, Unbounded},
+        RangeBounds,
+    },
+    sync::{
+        Arc,
+        atomic::{AtomicI32, Ordering},
+    },
+    time::Duration,
+};
 
 use bevy::{
     prelude::*,
     tasks::{Task, block_on, futures_lite::future},
 };
 
-use crate::decodable::VideoFrame;
+use crate::{
+    chapter::Chapter,
+    cue::CuePoint,
+    decodable::VideoFrame,
+    diagnostics::VideoStats,
+    frame_info::{FrameInfo, YuvPlanes},
+    scheduler::DecodePriority,
+    systems::DecodeTask,
+    throttle::{DecodeThrottle, OffscreenPolicy},
+};
 
 #[derive(Component)]
 pub struct DrainVideoSink;
@@ -18,6 +37,9 @@ pub struct DrainVideoSink;
 /// If this component is removed from an entity, and a [`VideoSource`][crate::VideoSource] is
 /// attached to that entity, that [`VideoSource`][crate::VideoSource] will start playing. If
 /// that source is unchanged, that translates to the video restarting.
+///
+/// If the [`VideoSource`][crate::VideoSource] is modified, e.g. by hot reloading,
+/// the video continues playing the new source from the same position.
 #[derive(Component)]
 pub struct VideoSink {
     image: Handle<Image>,
@@ -25,31 +47,305 @@ pub struct VideoSink {
     task: Task<Result<()>>,
     width: u32,
     height: u32,
+    display_aspect_ratio: f32,
+    /// The size or display aspect ratio changed without triggering [`VideoResized`],
+    /// e.g. when switching to a queued video.
+    resized: bool,
     frame_duration: Duration,
+    frame_count: Option<u64>,
+    duration: Option<Duration>,
     buffered_frame: Option<VideoFrame>,
     start_timestamp: Option<Duration>,
+    /// Timestamp of the frame displayed at `start_timestamp`, non-zero after seeking.
+    start_position: Duration,
+    /// Timestamp of the last frame received from the decoder.
+    last_timestamp: Option<Duration>,
+    /// Added to frame timestamps of decoders that restart timestamps when looping,
+    /// so that the timeline keeps increasing.
+    loop_offset: Duration,
+    /// Start of the range to play.
+    range_start: Duration,
+    /// Position in the video playback started from, frames before it are skipped.
+    played_from: Duration,
+    /// End of the range to play, playback ends at the first frame after it unless looping.
+    range_end: Option<Duration>,
+    /// Where playback restarts at the end of the range when looping.
+    loop_start: Duration,
+    looping: bool,
+    /// Playback reached the end of the range.
+    ended: bool,
+    /// Keep showing the last frame when the video finishes, instead of ending playback.
+    hold: bool,
+    /// Timestamp of the displayed frame.
+    position: Duration,
+    /// Position in the video of the displayed frame, without loop offsets.
+    video_position: Option<Duration>,
+    /// Position to seek to, see [`VideoSink::seek`].
+    seek: Option<Duration>,
+    /// Time from the start of the range set by a [`VideoClock`][crate::VideoClock] for the next frame.
+    clock_time: Option<Duration>,
+    cues: Vec<CuePoint>,
+    chapters: Vec<Chapter>,
+    frame_info: Option<FrameInfo>,
+    pub(crate) yuv_planes: Option<YuvPlanes>,
+    throttle: DecodeThrottle,
+    priority: Arc<AtomicI32>,
+    /// Paused by [`VideoSink::pause`].
+    paused: bool,
+    /// The time playback was paused, by [`VideoSink::pause`] or [`OffscreenPolicy::Pause`].
+    paused_at: Option<Duration>,
+    stats: VideoStats,
+    /// The next video to play when this one finishes, already decoding its first frame.
+    queued: Option<DecodeTask>,
 }
 
 impl VideoSink {
+    /// How far into a chapter [`VideoSink::previous_chapter`] restarts it, rather than
+    /// seeking to the previous chapter.
+    pub const PREVIOUS_CHAPTER_THRESHOLD: Duration = Duration::from_secs(3);
+
     /// Create a new video sink.
-    pub(crate) fn new(
-        image: Handle<Image>,
-        timebase: (u32, u32),
-        width: u32,
-        height: u32,
-        rx: async_channel::Receiver<VideoFrame>,
-        task: Task<Result<()>>,
-    ) -> Self {
+    pub(crate) fn new(image: Handle<Image>, decode_task: DecodeTask) -> Self {
+        let DecodeTask {
+            frame_duration,
+            width,
+            height,
+            display_aspect_ratio,
+            frame_count,
+            duration,
+            start,
+            end,
+            loop_start,
+            looping,
+            cues,
+            chapters,
+            rx,
+            task,
+        } = decode_task;
         Self {
             image,
-            frame_duration: Duration::from_secs_f64(timebase.0 as f64 / timebase.1 as f64),
+            frame_duration,
+            frame_count,
+            duration,
             rx,
             task,
             width,
             height,
+            display_aspect_ratio,
+            resized: false,
             buffered_frame: None,
             start_timestamp: None,
+            start_position: Duration::ZERO,
+            last_timestamp: None,
+            loop_offset: Duration::ZERO,
+            range_start: start,
+            played_from: start,
+            range_end: end,
+            loop_start,
+            looping,
+            ended: false,
+            hold: false,
+            position: Duration::ZERO,
+            video_position: None,
+            seek: None,
+            clock_time: None,
+            cues,
+            chapters,
+            frame_info: None,
+            yuv_planes: None,
+            throttle: DecodeThrottle::default(),
+            priority: Arc::default(),
+            paused: false,
+            paused_at: None,
+            stats: VideoStats::default(),
+            queued: None,
+        }
+    }
+
+    /// Share `throttle` with the decoder of this sink.
+    pub(crate) fn with_throttle(mut self, throttle: DecodeThrottle) -> Self {
+        self.throttle = throttle;
+        self
+    }
+
+    /// Share the scheduling `priority` with the decode task of this sink.
+    pub(crate) fn with_priority(mut self, priority: Arc<AtomicI32>) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub(crate) fn set_priority(&self, priority: DecodePriority) {
+        self.priority.store(priority.0, Ordering::Relaxed);
+    }
+
+    /// Apply the offscreen `policy` in effect at `current_time`.
+    pub(crate) fn set_throttle(&mut self, policy: OffscreenPolicy, current_time: Duration) {
+        if self.throttle.get() == policy {
+            return;
+        }
+        match (policy, self.paused_at) {
+            (OffscreenPolicy::Pause, None) => self.paused_at = Some(current_time),
+            (OffscreenPolicy::Pause, Some(_)) => (),
+            // Resume from the frame playback was paused at
+            (_, Some(paused_at)) => {
+                if let Some(start_timestamp) = &mut self.start_timestamp {
+                    *start_timestamp += current_time.saturating_sub(paused_at);
+                }
+                self.paused_at = None;
+            }
+            (_, None) => (),
+        }
+        self.throttle.set(policy);
+    }
+
+    /// The throttle shared with the decoder of this sink.
+    pub(crate) fn throttle(&self) -> &DecodeThrottle {
+        &self.throttle
+    }
+
+    /// The scheduling priority shared with the decode task of this sink.
+    pub(crate) fn priority(&self) -> &Arc<AtomicI32> {
+        &self.priority
+    }
+
+    /// Play `decode_task` into the same image when the current video finishes.
+    pub(crate) fn queue(&mut self, decode_task: DecodeTask) {
+        self.queued = Some(decode_task);
+    }
+
+    /// Whether a video is queued to play when the current video finishes.
+    pub(crate) fn has_queued(&self) -> bool {
+        self.queued.is_some()
+    }
+
+    /// Switch to the queued video, returns `false` if none is queued.
+    pub(crate) fn play_queued(&mut self) -> bool {
+        let Some(decode_task) = self.queued.take() else {
+            return false;
+        };
+        let start = decode_task.start;
+        self.reset_for(decode_task, start);
+        true
+    }
+
+    /// Play `decode_task` from `position`, replacing the properties and playback state
+    /// of the previous video.
+    fn reset_for(&mut self, decode_task: DecodeTask, position: Duration) {
+        self.resized |= (decode_task.width, decode_task.height) != (self.width, self.height)
+            || decode_task.display_aspect_ratio != self.display_aspect_ratio;
+        self.width = decode_task.width;
+        self.height = decode_task.height;
+        self.display_aspect_ratio = decode_task.display_aspect_ratio;
+        self.frame_duration = decode_task.frame_duration;
+        self.frame_count = decode_task.frame_count;
+        self.duration = decode_task.duration;
+        self.rx = decode_task.rx;
+        self.task = decode_task.task;
+        self.buffered_frame = None;
+        self.start_timestamp = None;
+        self.start_position = position.saturating_sub(decode_task.start);
+        self.last_timestamp = None;
+        self.loop_offset = Duration::ZERO;
+        self.range_start = decode_task.start;
+        self.played_from = position;
+        self.range_end = decode_task.end;
+        self.loop_start = decode_task.loop_start;
+        self.looping = decode_task.looping;
+        self.ended = false;
+        self.hold = false;
+        self.position = self.start_position;
+        self.video_position = None;
+        self.seek = None;
+        self.cues = decode_task.cues;
+        self.chapters = decode_task.chapters;
+    }
+
+    /// Seek to `time` from the start of the video.
+    ///
+    /// The video restarts decoding from the closest key frame, so the new frame
+    /// is displayed after a short delay. Times before the start of the
+    /// [`VideoRange`][crate::VideoRange] seek to its start, times past its end seek to
+    /// the last frame, or to the loop start when looping.
+    pub fn seek(&mut self, time: Duration) {
+        let mut time = time.max(self.range_start);
+        if let Some(end) = self.range_end.or(self.duration) {
+            if self.looping && time >= end {
+                time = self.loop_start;
+            } else {
+                let last_frame = end.saturating_sub(self.frame_duration);
+                time = time.min(last_frame.max(self.range_start));
+            }
         }
+        self.seek = Some(time);
+    }
+
+    /// Start of the range to play, from the start of the video.
+    pub(crate) fn range_start(&self) -> Duration {
+        self.range_start
+    }
+
+    /// Display the frame at `time` from the start of the range, instead of following the app clock.
+    pub(crate) fn set_clock_time(&mut self, time: Option<Duration>) {
+        self.clock_time = time;
+    }
+
+    /// Whether the frame at the current position has been decoded, or playback ended.
+    pub(crate) fn is_ready(&mut self) -> bool {
+        if self.seek.is_some() {
+            return false;
+        }
+        if self.video_position.is_some() || self.ended {
+            return true;
+        }
+        if self.buffered_frame.is_none() {
+            self.buffered_frame = self.fetch_frame();
+        }
+        self.buffered_frame.is_some()
+    }
+
+    /// Whether a seek is pending, or has not displayed a frame yet.
+    #[cfg(feature = "bevy_ui")]
+    pub(crate) fn is_seeking(&self) -> bool {
+        self.seek.is_some() || self.video_position.is_none()
+    }
+
+    /// Take the pending seek, see [`VideoSink::seek`].
+    pub(crate) fn take_seek(&mut self) -> Option<Duration> {
+        self.seek.take()
+    }
+
+    /// Continue playback of the same video from `position` with `decode_task`, started there.
+    /// The video may have been reloaded, so its properties are updated.
+    pub(crate) fn restart(&mut self, decode_task: DecodeTask, position: Duration) {
+        // The decode task starts at `position`, the range still starts where it did
+        let start = self.range_start;
+        self.reset_for(
+            DecodeTask {
+                start,
+                ..decode_task
+            },
+            position,
+        );
+    }
+
+    /// Pause playback, keeping the current frame displayed.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resume playback after [`VideoSink::pause`].
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Pause if playing, resume if paused.
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// Whether playback is paused, by [`VideoSink::pause`] or because the video is offscreen.
+    pub fn is_paused(&self) -> bool {
+        self.paused || self.paused_at.is_some()
     }
 
     pub(crate) fn poll_task(&mut self) -> Option<Result<()>> {
@@ -57,21 +353,143 @@ impl VideoSink {
     }
 
     fn fetch_frame(&mut self) -> Option<VideoFrame> {
-        let frame = match self.buffered_frame.take() {
-            Some(frame) => frame,
-            None => self.rx.try_recv().ok()?,
-        };
-        // Support looping
-        if frame.timestamp == Duration::ZERO {
-            self.start_timestamp = None;
+        if let Some(frame) = self.buffered_frame.take() {
+            return Some(frame);
         }
-        Some(frame)
+        while !self.ended {
+            let mut frame = self.rx.try_recv().ok()?;
+            self.stats.decoded += 1;
+            self.stats.decode_time += frame.decode_time;
+            self.stats.conversion_time += frame.conversion_time;
+            // Support looping decoders that restart from zero
+            if let Some(last_timestamp) = self.last_timestamp
+                && frame.timestamp < last_timestamp
+            {
+                self.loop_offset += last_timestamp + self.frame_duration;
+            }
+            self.last_timestamp = Some(frame.timestamp);
+            frame.position = Some(frame.position.unwrap_or(frame.timestamp));
+            frame.timestamp += self.loop_offset;
+            // Decoders may output frames outside the range
+            if frame.timestamp < self.played_from {
+                continue;
+            }
+            if !self.looping && self.range_end.is_some_and(|end| frame.timestamp >= end) {
+                self.ended = true;
+                break;
+            }
+            frame.timestamp -= self.range_start;
+            return Some(frame);
+        }
+        None
+    }
+
+    /// Whether playback reached the end of the range of the video.
+    pub(crate) fn is_ended(&self) -> bool {
+        self.ended
+    }
+
+    /// Keep showing the last frame when the video finishes, e.g. while waiting for a video to queue.
+    pub(crate) fn set_hold(&mut self, hold: bool) {
+        self.hold = hold;
+    }
+
+    pub(crate) fn is_held(&self) -> bool {
+        self.hold
+    }
+
+    /// The timestamp of the displayed frame, from the start of the range.
+    pub(crate) fn range_position(&self) -> Duration {
+        self.position
+    }
+
+    /// The position in the video of the displayed frame, `None` before the first frame.
+    pub(crate) fn video_position(&self) -> Option<Duration> {
+        self.video_position
+    }
+
+    /// The time of the displayed frame from the start of the video.
+    ///
+    /// This restarts from the loop start when looping,
+    /// and is the start of the [`VideoRange`][crate::VideoRange] before the first frame.
+    pub fn position(&self) -> Duration {
+        self.video_position.unwrap_or(self.played_from)
+    }
+
+    /// The index of the displayed frame from the start of the video, see [`VideoSink::position`].
+    pub fn frame_index(&self) -> u64 {
+        (self.position().as_secs_f64() / self.frame_duration.as_secs_f64()).round() as u64
+    }
+
+    /// The duration of the whole video, if known.
+    /// For AV1 this is measured by scanning the container when the video is loaded.
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    /// The number of frames in the whole video, if known.
+    pub fn frame_count(&self) -> Option<u64> {
+        self.frame_count
+    }
+
+    /// The cue points crossed by playback from `previous` to the displayed frame, in order.
+    /// Playback moving backwards means the video looped.
+    pub(crate) fn crossed_cue_points(
+        &self,
+        previous: Option<Duration>,
+    ) -> impl Iterator<Item = &CuePoint> {
+        let current = self.video_position.unwrap_or_default();
+        let end = self.range_end.map_or(Unbounded, Excluded);
+        let (before_loop, after_loop) = match previous {
+            // Started playing
+            None => (None, (Included(self.played_from), Included(current))),
+            Some(previous) if previous <= current => {
+                (None, (Excluded(previous), Included(current)))
+            }
+            Some(previous) => (
+                Some((Excluded(previous), end)),
+                (Included(self.loop_start), Included(current)),
+            ),
+        };
+        let cues_in = |range: (Bound<Duration>, Bound<Duration>)| {
+            self.cues
+                .iter()
+                .filter(move |cue| range.contains(&cue.time))
+        };
+        before_loop
+            .into_iter()
+            .flat_map(cues_in)
+            .chain(cues_in(after_loop))
     }
 
     pub(crate) fn next_frame(&mut self, current_time: Duration) -> Option<VideoFrame> {
+        match (self.paused, self.paused_at) {
+            (true, None) => self.paused_at = Some(current_time),
+            // Resume from the frame playback was paused at, unless still offscreen
+            (false, Some(paused_at)) if self.throttle.get() != OffscreenPolicy::Pause => {
+                if let Some(start_timestamp) = &mut self.start_timestamp {
+                    *start_timestamp += current_time.saturating_sub(paused_at);
+                }
+                self.paused_at = None;
+            }
+            _ => (),
+        }
+        if self.is_paused() {
+            // While paused the channel fills up, which blocks the decode task
+            if self.video_position.is_some() || !self.paused {
+                return None;
+            }
+            // Display the first frame after seeking while paused
+            self.paused_at = Some(current_time);
+        }
+        // Follow the clock, and continue from its time if it goes away
+        if let Some(clock_time) = self.clock_time.take() {
+            self.start_timestamp = Some(current_time);
+            self.start_position = clock_time;
+        }
         while let Some(frame) = self.fetch_frame() {
             let start_timestamp = self.start_timestamp.get_or_insert(current_time);
-            let elapsed = current_time - *start_timestamp;
+            let elapsed = current_time - *start_timestamp + self.start_position;
 
             // Frame in the future
             if frame.timestamp > elapsed + self.frame_duration {
@@ -80,34 +498,174 @@ impl VideoSink {
             }
             // Frame too old, discard
             else if frame.timestamp + self.frame_duration < elapsed {
+                self.stats.dropped += 1;
                 continue;
             }
             // Frame is current
+            self.stats.presented += 1;
+            self.position = frame.timestamp;
+            self.video_position = frame.position;
             return Some(frame);
         }
         None
     }
 
+    /// Record that `bytes` of image data were uploaded.
+    pub(crate) fn record_upload(&mut self, bytes: usize) {
+        self.stats.bytes_uploaded += bytes;
+    }
+
+    /// Take the playback statistics since they were last taken.
+    pub(crate) fn take_stats(&mut self) -> VideoStats {
+        let stats = VideoStats {
+            queue_depth: self.rx.len() + self.buffered_frame.is_some() as usize,
+            ..self.stats.clone()
+        };
+        self.stats = VideoStats {
+            dropped: self.stats.dropped,
+            ..default()
+        };
+        stats
+    }
+
+    /// The position in the video used to navigate, the pending seek if any.
+    fn navigation_position(&self) -> Duration {
+        self.seek
+            .or(self.video_position)
+            .unwrap_or(self.played_from)
+    }
+
+    /// The chapters of the video, sorted by start time, see [`Chapter`].
+    pub fn chapters(&self) -> &[Chapter] {
+        &self.chapters
+    }
+
+    /// The chapter being played, if any.
+    pub fn current_chapter(&self) -> Option<&Chapter> {
+        let position = self.navigation_position();
+        self.chapters
+            .iter()
+            .take_while(|chapter| chapter.start <= position)
+            .last()
+    }
+
+    /// Seek to the start of the next chapter, returns `false` if this is the last chapter.
+    pub fn next_chapter(&mut self) -> bool {
+        let position = self.navigation_position();
+        let Some(next) = self
+            .chapters
+            .iter()
+            .find(|chapter| chapter.start > position)
+        else {
+            return false;
+        };
+        self.seek(next.start);
+        true
+    }
+
+    /// Seek to the start of the previous chapter, returns `false` if this is the first chapter.
+    ///
+    /// Like most media players, this restarts the current chapter instead
+    /// if it has been playing for more than [`VideoSink::PREVIOUS_CHAPTER_THRESHOLD`].
+    pub fn previous_chapter(&mut self) -> bool {
+        let position = self.navigation_position();
+        let current = self
+            .chapters
+            .iter()
+            .rposition(|chapter| chapter.start <= position);
+        let previous = match current {
+            Some(current)
+                if position - self.chapters[current].start > Self::PREVIOUS_CHAPTER_THRESHOLD =>
+            {
+                current
+            }
+            Some(current) if current > 0 => current - 1,
+            _ => return false,
+        };
+        self.seek(self.chapters[previous].start);
+        true
+    }
+
+    /// The number of frames that were decoded too late to be displayed.
+    pub fn dropped_frames(&self) -> u64 {
+        self.stats.dropped
+    }
+
     /// Width of a video frame.
+    /// This changes if the video changes resolution, see [`VideoResized`].
     pub fn width(&self) -> u32 {
         self.width
     }
 
     /// Height of a video frame.
+    /// This changes if the video changes resolution, see [`VideoResized`].
     pub fn height(&self) -> u32 {
         self.height
     }
 
+    /// Update the frame size, returns `true` if it changed, or the size or display aspect ratio
+    /// changed since the last call, see [`VideoResized`].
+    pub(crate) fn resize(&mut self, size: UVec2) -> bool {
+        let changed = self.resized || size != UVec2::new(self.width, self.height);
+        self.width = size.x;
+        self.height = size.y;
+        self.resized = false;
+        changed
+    }
+
+    /// The intended display width divided by display height.
+    ///
+    /// Use this rather than [`VideoSink::width`] / [`VideoSink::height`] to size the surface
+    /// displaying the video, as anamorphic video is stretched when displayed.
+    pub fn display_aspect_ratio(&self) -> f32 {
+        self.display_aspect_ratio
+    }
+
+    /// Update state from the metadata of the displayed frame.
+    pub(crate) fn set_frame_info(&mut self, frame_info: Option<FrameInfo>) {
+        if let Some(frame_info) = &frame_info {
+            self.display_aspect_ratio = frame_info.display_aspect_ratio;
+        }
+        self.frame_info = frame_info;
+    }
+
     /// The texture handle that video frames are rendered into.
     /// Apply this to a material or sprite to make the video visible.
     pub fn image(&self) -> &Handle<Image> {
         &self.image
     }
+
+    /// Metadata describing the currently displayed frame, if provided by the decoder.
+    pub fn frame_info(&self) -> Option<&FrameInfo> {
+        self.frame_info.as_ref()
+    }
+
+    /// The raw YUV planes of the currently displayed frame.
+    /// Only available if requested by [`DecodeSettings::yuv_planes`][crate::DecodeSettings::yuv_planes].
+    pub fn yuv_planes(&self) -> Option<&YuvPlanes> {
+        self.yuv_planes.as_ref()
+    }
 }
 
 #[derive(Event, Debug)]
 pub struct VideoFrameUpdated(pub AssetId<Image>);
 
+/// Triggered on the entity of a [`VideoSink`] when the video changes resolution mid-stream,
+/// e.g. at a switch point of an adaptive stream, or when a queued video of a different size
+/// or aspect ratio starts playing, e.g. the next item of a [`VideoPlaylist`][crate::VideoPlaylist].
+///
+/// The [`VideoSink::image`] has been reallocated at the new size when this is triggered,
+/// observe this to relayout sprites or UI displaying the video.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct VideoResized {
+    /// The new width of a video frame.
+    pub width: u32,
+    /// The new height of a video frame.
+    pub height: u32,
+    /// The new display aspect ratio, see [`VideoSink::display_aspect_ratio`].
+    pub display_aspect_ratio: f32,
+}
+
 /// Stores target [`AssetId`]s of assets that have a dependency on the video [`Image`] asset.
 ///
 /// e.g. if you store the [`VideoSink::image`] in `StandardMaterial::base_color_texture`
@@ -1,15 +1,97 @@
-use crate::{av1, decodable::Decodable};
+use crate::{
+    av1::{self, decoder::StreamIndex},
+    chapter::{Chapter, chapters},
+    cue::{CuePoint, cue_points},
+    decodable::{Decodable, DecodeSettings, VideoFrame},
+};
 use bevy::{
-    asset::{AssetLoader, LoadContext, io::Reader},
+    asset::{AssetLoader, LoadContext, RenderAssetUsages, io::Reader},
     prelude::*,
+    render::render_resource::{Extent3d, TextureDimension},
 };
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use std::io::Cursor;
 use std::sync::Arc;
+use std::time::Duration;
 
 /// A source of video data.
 #[derive(Asset, Debug, Clone, Reflect)]
 pub struct VideoSource {
     pub bytes: Arc<[u8]>,
+    /// How transparency is packed into the frames of the video.
+    pub alpha_layout: AlphaLayout,
+    /// A separate monochrome video stream whose luma is the alpha channel of this video.
+    /// It must have the same dimensions and frame count as this video.
+    pub alpha: Option<Arc<[u8]>>,
+    /// Named moments in the video, sorted by time, see [`VideoLoaderSettings::cues_path`].
+    pub cues: Vec<CuePoint>,
+    /// The chapters of the video, sorted by start time, see [`VideoLoaderSettings::chapters_path`].
+    pub chapters: Vec<Chapter>,
+    /// Indexes of the video and alpha streams, built when first needed and shared
+    /// by every decoder of this source, e.g. for each seek.
+    #[reflect(ignore)]
+    index: Arc<StreamIndex>,
+    #[reflect(ignore)]
+    alpha_index: Arc<StreamIndex>,
+}
+
+/// How transparency is encoded in a [`VideoSource`].
+///
+/// Alpha is encoded as luma, white is opaque and black is transparent.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum AlphaLayout {
+    /// No alpha is packed into the frames.
+    #[default]
+    Opaque,
+    /// Color is in the left half of each frame, alpha in the right half.
+    SideBySide,
+    /// Color is in the top half of each frame, alpha in the bottom half.
+    Stacked,
+}
+
+impl VideoSource {
+    /// Creates an opaque [`VideoSource`] from encoded video data.
+    pub fn new(bytes: impl Into<Arc<[u8]>>) -> Self {
+        Self {
+            bytes: bytes.into(),
+            alpha_layout: AlphaLayout::Opaque,
+            alpha: None,
+            cues: Vec::new(),
+            chapters: Vec::new(),
+            index: Arc::default(),
+            alpha_index: Arc::default(),
+        }
+    }
+
+    fn av1_decoder(
+        &self,
+        settings: &DecodeSettings,
+    ) -> Result<av1::Decoder<Cursor<VideoSource>>, av1::Error> {
+        let decoder = av1::Decoder::new(Cursor::new(self.clone()), settings)?
+            .with_alpha_layout(self.alpha_layout)
+            .with_index(self.index.clone())?;
+        match &self.alpha {
+            Some(alpha) => decoder.with_alpha_stream(
+                Cursor::new(VideoSource::new(alpha.clone())),
+                self.alpha_index.clone(),
+            ),
+            None => Ok(decoder),
+        }
+    }
+
+    /// Returns a blocking iterator that decodes the frames of this video.
+    ///
+    /// This does not require an [`App`] or task pool,
+    /// so it can be used to decode videos in tools, build scripts and tests.
+    pub fn frames(&self) -> Result<VideoFrames> {
+        self.frames_with_settings(&DecodeSettings::default())
+    }
+
+    /// Returns a blocking iterator that decodes the frames of this video,
+    /// configured with `settings`, see [`VideoSource::frames`].
+    pub fn frames_with_settings(&self, settings: &DecodeSettings) -> Result<VideoFrames> {
+        Ok(VideoFrames(self.av1_decoder(settings)?))
+    }
 }
 
 impl AsRef<[u8]> for VideoSource {
@@ -21,8 +103,84 @@ impl AsRef<[u8]> for VideoSource {
 impl Decodable for VideoSource {
     type Decoder = av1::Decoder<Cursor<VideoSource>>;
 
-    fn decoder(&self) -> Self::Decoder {
-        av1::Decoder::new(Cursor::new(self.clone())).unwrap()
+    fn decoder(&self, settings: &DecodeSettings) -> Self::Decoder {
+        self.av1_decoder(settings).unwrap()
+    }
+
+    fn cue_points(&self) -> &[CuePoint] {
+        &self.cues
+    }
+
+    fn chapters(&self) -> &[Chapter] {
+        &self.chapters
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        self.index.duration()
+    }
+}
+
+/// A blocking iterator over the decoded [`VideoFrame`]s of a [`VideoSource`].
+///
+/// Created by [`VideoSource::frames`].
+pub struct VideoFrames(av1::Decoder<Cursor<VideoSource>>);
+
+impl Iterator for VideoFrames {
+    type Item = Result<VideoFrame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_frame().map_err(BevyError::from).transpose()
+    }
+}
+
+/// Settings for [`VideoSource`] loading.
+///
+/// Frames decoded at load time are added as labeled [`Image`] sub-assets of the [`VideoSource`].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VideoLoaderSettings {
+    /// Timestamp of the frame decoded into the `poster` labeled [`Image`],
+    /// e.g. `video.ivf#poster`. `None`, the default, disables the poster frame.
+    pub poster: Option<Duration>,
+    /// Timestamps of additional frames to decode into labeled [`Image`]s.
+    /// These are labeled with the timestamp in seconds rounded to milliseconds,
+    /// e.g. `video.ivf#frame@2.5s`. Of timestamps with the same label, only the earliest is decoded.
+    pub frames: Vec<Duration>,
+    /// Decode every frame into a grid in an `atlas` labeled [`Image`],
+    /// with a corresponding `atlas_layout` labeled [`TextureAtlasLayout`].
+    /// Intended for short clips, the atlas must fit within the GPU texture size limits.
+    pub atlas: bool,
+    /// Number of columns in the `atlas` grid, if `None` the grid is roughly square.
+    pub atlas_columns: Option<u32>,
+    /// Decode every frame into the layers of an `array` labeled 2D array texture [`Image`].
+    pub texture_array: bool,
+    /// How transparency is packed into the frames of the video.
+    pub alpha_layout: AlphaLayout,
+    /// Path to a separate monochrome video providing the alpha channel,
+    /// relative to the video being loaded. See [`VideoSource::alpha`].
+    pub alpha_path: Option<String>,
+    /// Path to a sidecar file of [`CuePoint`]s, relative to the video being loaded.
+    /// A RON list, or JSON if the extension is `.json`, of cue names and times in seconds,
+    /// e.g. `[(name: "boom", time: 2.5)]`.
+    pub cues_path: Option<String>,
+    /// Path to a sidecar file of [`Chapter`]s, relative to the video being loaded.
+    /// A RON list, or JSON if the extension is `.json`, of chapter titles and start times in seconds,
+    /// e.g. `[(title: "Movement", start: 0.0), (title: "Combat", start: 95.5)]`.
+    pub chapters_path: Option<String>,
+}
+
+impl Default for VideoLoaderSettings {
+    fn default() -> Self {
+        Self {
+            poster: None,
+            frames: Vec::new(),
+            atlas: false,
+            atlas_columns: None,
+            texture_array: false,
+            alpha_layout: AlphaLayout::Opaque,
+            alpha_path: None,
+            cues_path: None,
+            chapters_path: None,
+        }
     }
 }
 
@@ -34,20 +192,106 @@ pub struct VideoLoader;
 
 impl AssetLoader for VideoLoader {
     type Asset = VideoSource;
-    type Settings = ();
-    type Error = std::io::Error;
+    type Settings = VideoLoaderSettings;
+    type Error = av1::Error;
 
     async fn load(
         &self,
         reader: &mut dyn Reader,
-        _settings: &Self::Settings,
-        _load_context: &mut LoadContext<'_>,
+        settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
     ) -> Result<VideoSource, Self::Error> {
         let mut bytes = Vec::new();
-        reader.read_to_end(&mut bytes).await?;
-        Ok(VideoSource {
-            bytes: bytes.into(),
-        })
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(av1::Error::Io)?;
+        let alpha = match &settings.alpha_path {
+            Some(alpha_path) => {
+                let alpha_path = load_context
+                    .asset_path()
+                    .resolve_embed(alpha_path)
+                    .map_err(|e| av1::Error::Io(std::io::Error::other(e)))?;
+                let alpha = load_context
+                    .read_asset_bytes(alpha_path)
+                    .await
+                    .map_err(|e| av1::Error::Io(std::io::Error::other(e)))?;
+                Some(alpha.into())
+            }
+            None => None,
+        };
+        let cues = match &settings.cues_path {
+            Some(cues_path) => {
+                cue_points(read_sidecar(load_context, cues_path).await?).map_err(av1::Error::Io)?
+            }
+            None => Vec::new(),
+        };
+        let chapters = match &settings.chapters_path {
+            Some(chapters_path) => chapters(read_sidecar(load_context, chapters_path).await?)
+                .map_err(av1::Error::Io)?,
+            None => Vec::new(),
+        };
+        let source = VideoSource {
+            alpha_layout: settings.alpha_layout,
+            alpha,
+            cues,
+            chapters,
+            ..VideoSource::new(bytes)
+        };
+        // Measure the video while loading, rather than each time playback starts
+        source.index.prime_length(Cursor::new(source.clone()))?;
+        // Otherwise an invalid alpha stream would only fail once playback starts
+        if let Some(alpha) = &source.alpha {
+            source
+                .alpha_index
+                .prime_length(Cursor::new(VideoSource::new(alpha.clone())))?;
+        }
+
+        let mut frames = settings.frames.clone();
+        frames.sort();
+        // Timestamps with the same label would overwrite each other, only decode the first
+        frames.dedup_by_key(|timestamp| frame_label(*timestamp));
+        let mut labels: Vec<(String, Duration)> = settings
+            .poster
+            .iter()
+            .map(|&timestamp| ("poster".to_string(), timestamp))
+            .chain(
+                frames
+                    .into_iter()
+                    .map(|timestamp| (frame_label(timestamp), timestamp)),
+            )
+            .collect();
+        if !labels.is_empty() {
+            labels.sort_by_key(|&(_, timestamp)| timestamp);
+            let timestamps: Vec<Duration> = labels.iter().map(|&(_, t)| t).collect();
+            let frames = source
+                .av1_decoder(&DecodeSettings::default())?
+                .frames_at(&timestamps)?;
+            for ((label, _), frame) in labels.into_iter().zip(frames) {
+                load_context.add_labeled_asset(label, frame.image);
+            }
+        }
+
+        if settings.atlas || settings.texture_array {
+            let mut decoder = source.av1_decoder(&DecodeSettings::default())?;
+            let mut frames = Vec::new();
+            while let Some(frame) = decoder.next_frame()? {
+                frames.push(frame);
+            }
+            if settings.atlas
+                && let Some((image, layout)) = atlas(&frames, settings.atlas_columns)
+            {
+                load_context.add_labeled_asset("atlas".to_string(), image);
+                load_context.add_labeled_asset("atlas_layout".to_string(), layout);
+            }
+            if settings.texture_array
+                && let Some(image) = texture_array(&frames)
+            {
+                load_context.add_labeled_asset("array".to_string(), image);
+            }
+        }
+
+        Ok(source)
     }
 
     fn extensions(&self) -> &[&str] {
@@ -55,6 +299,106 @@ impl AssetLoader for VideoLoader {
     }
 }
 
+/// Read and deserialize the RON, or JSON if the extension is `.json`, file at `path`
+/// relative to the asset being loaded.
+async fn read_sidecar<T: DeserializeOwned>(
+    load_context: &mut LoadContext<'_>,
+    path: &str,
+) -> Result<T, av1::Error> {
+    let path = load_context
+        .asset_path()
+        .resolve_embed(path)
+        .map_err(|e| av1::Error::Io(std::io::Error::other(e)))?;
+    let json = path
+        .get_full_extension()
+        .is_some_and(|extension| extension.ends_with("json"));
+    let bytes = load_context
+        .read_asset_bytes(path)
+        .await
+        .map_err(|e| av1::Error::Io(std::io::Error::other(e)))?;
+    if json {
+        serde_json::from_slice(&bytes).map_err(|e| av1::Error::Io(std::io::Error::other(e)))
+    } else {
+        ron::de::from_bytes(&bytes).map_err(|e| av1::Error::Io(std::io::Error::other(e)))
+    }
+}
+
+/// The sub-asset label of the frame decoded at `timestamp`, see [`VideoLoaderSettings::frames`].
+///
+/// Rounded to milliseconds, so `Duration::from_secs_f32(2.2)` is labeled `frame@2.2s`
+/// rather than `frame@2.200000047s`.
+fn frame_label(timestamp: Duration) -> String {
+    let millis = (timestamp.as_nanos() + 500_000) / 1_000_000;
+    format!("frame@{}s", millis as f64 / 1000.0)
+}
+
+/// The first frame image, and the image data of all frames the same size as the first.
+/// Frames that differ in size from the first frame, or have no data, are skipped.
+fn uniform_frames(frames: &[VideoFrame]) -> Option<(&Image, Vec<&[u8]>)> {
+    let first = &frames.first()?.image;
+    let data = frames
+        .iter()
+        .filter(|frame| frame.image.size() == first.size())
+        .filter_map(|frame| frame.image.data.as_deref())
+        .collect::<Vec<_>>();
+    (!data.is_empty()).then_some((first, data))
+}
+
+/// Pack all frames into a grid, in a single image.
+fn atlas(frames: &[VideoFrame], columns: Option<u32>) -> Option<(Image, TextureAtlasLayout)> {
+    let (first, data) = uniform_frames(frames)?;
+    let size = first.size();
+    let count = data.len() as u32;
+    let columns = columns
+        .unwrap_or_else(|| (count as f32).sqrt().ceil() as u32)
+        .clamp(1, count);
+    let rows = count.div_ceil(columns);
+    let row_len = data[0].len() / size.y as usize;
+    let atlas_row_len = row_len * columns as usize;
+
+    let mut atlas_data = vec![0; atlas_row_len * (size.y * rows) as usize];
+    for (i, frame_data) in data.iter().enumerate() {
+        let column = i % columns as usize;
+        let row = i / columns as usize;
+        for (y, frame_row) in frame_data.chunks_exact(row_len).enumerate() {
+            let offset = (row * size.y as usize + y) * atlas_row_len + column * row_len;
+            atlas_data[offset..offset + row_len].copy_from_slice(frame_row);
+        }
+    }
+
+    let image = Image::new(
+        Extent3d {
+            width: size.x * columns,
+            height: size.y * rows,
+            ..default()
+        },
+        TextureDimension::D2,
+        atlas_data,
+        first.texture_descriptor.format,
+        RenderAssetUsages::default(),
+    );
+    let mut layout = TextureAtlasLayout::from_grid(size, columns, rows, None, None);
+    // The last row may not be full
+    layout.textures.truncate(count as usize);
+    Some((image, layout))
+}
+
+/// Stack all frames as the layers of a 2D array texture.
+fn texture_array(frames: &[VideoFrame]) -> Option<Image> {
+    let (first, data) = uniform_frames(frames)?;
+    Some(Image::new(
+        Extent3d {
+            width: first.width(),
+            height: first.height(),
+            depth_or_array_layers: data.len() as u32,
+        },
+        TextureDimension::D2,
+        data.concat(),
+        first.texture_descriptor.format,
+        RenderAssetUsages::default(),
+    ))
+}
+
 /// A trait that allows adding a custom video source to the object.
 /// This is implemented for [`App`] to allow registering custom [`Decodable`] types.
 pub trait AddVideoSource {
@@ -68,3 +412,67 @@ pub trait AddVideoSource {
     where
         T: Decodable + Asset;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::asset::{
+        AssetPlugin, LoadState,
+        io::{
+            AssetSource, AssetSourceId,
+            memory::{Dir, MemoryAssetReader},
+        },
+    };
+    use std::path::Path;
+
+    /// Load `video.ivf` with the `alpha` stream from memory.
+    fn load_with_alpha(alpha: Vec<u8>) -> LoadState {
+        let video = std::fs::read(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/av1/cosmos-laundromat.ivf"),
+        )
+        .unwrap();
+        let dir = Dir::default();
+        dir.insert_asset(Path::new("video.ivf"), video);
+        dir.insert_asset(Path::new("alpha.ivf"), alpha);
+        let mut app = App::new();
+        app.register_asset_source(
+            AssetSourceId::Default,
+            AssetSource::build()
+                .with_reader(move || Box::new(MemoryAssetReader { root: dir.clone() })),
+        )
+        .add_plugins((TaskPoolPlugin::default(), AssetPlugin::default()))
+        .init_asset::<VideoSource>()
+        .register_asset_loader(VideoLoader);
+        let handle = app
+            .world()
+            .resource::<AssetServer>()
+            .load_with_settings::<VideoSource, VideoLoaderSettings>("video.ivf", |settings| {
+                settings.alpha_path = Some("alpha.ivf".to_string())
+            });
+        loop {
+            app.update();
+            let state = app.world().resource::<AssetServer>().load_state(&handle);
+            if !matches!(state, LoadState::Loading | LoadState::NotLoaded) {
+                return state;
+            }
+        }
+    }
+
+    #[test]
+    fn load_alpha_stream() {
+        let alpha = std::fs::read(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/av1/cosmos-laundromat.ivf"),
+        )
+        .unwrap();
+        assert!(matches!(load_with_alpha(alpha.clone()), LoadState::Loaded));
+        assert!(matches!(
+            load_with_alpha(b"not a video".to_vec()),
+            LoadState::Failed(_)
+        ));
+        // Truncated in the header
+        assert!(matches!(
+            load_with_alpha(alpha[..16].to_vec()),
+            LoadState::Failed(_)
+        ));
+    }
+}
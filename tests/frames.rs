@@ -0,0 +1,59 @@
+use std::path::Path;
+
+use bevy_av1::VideoSource;
+
+/// Number of packets in the IVF container, from the packet headers.
+fn packet_count(bytes: &[u8]) -> usize {
+    let header_len = u16::from_le_bytes([bytes[6], bytes[7]]) as usize;
+    let mut offset = header_len;
+    let mut count = 0;
+    while offset + 12 <= bytes.len() {
+        let len = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize;
+        offset += 12 + len;
+        count += 1;
+    }
+    count
+}
+
+#[test]
+fn frames() {
+    let mut videos = 0;
+    for entry in
+        std::fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/av1")).unwrap()
+    {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|extension| extension != "ivf") {
+            continue;
+        }
+        let bytes = std::fs::read(&path).unwrap();
+        let packets = packet_count(&bytes);
+        let source = VideoSource::new(bytes);
+
+        let mut count = 0;
+        let mut size = None;
+        let mut last_timestamp = None;
+        for frame in source.frames().unwrap() {
+            let frame = frame.unwrap();
+            let frame_size = frame.image.size();
+            assert!(
+                frame_size.x > 0 && frame_size.y > 0,
+                "{path:?} frame {count} is empty"
+            );
+            assert_eq!(
+                *size.get_or_insert(frame_size),
+                frame_size,
+                "{path:?} frame {count} changed size"
+            );
+            assert!(
+                last_timestamp.is_none_or(|last| last < frame.timestamp),
+                "{path:?} frame {count} timestamp {:?} is not after {last_timestamp:?}",
+                frame.timestamp
+            );
+            last_timestamp = Some(frame.timestamp);
+            count += 1;
+        }
+        assert_eq!(count, packets, "{path:?} frame count");
+        videos += 1;
+    }
+    assert!(videos > 0, "no videos in assets/av1");
+}